@@ -0,0 +1,43 @@
+use crate::Path;
+use serde_json::Value;
+
+/// The decision of a `ValueComparator` for two values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// The values are equal, nothing below them is compared.
+    Equal,
+    /// The values are different, they are reported as one replace.
+    Different,
+    /// No opinion, compare the values as usual.
+    Recurse,
+}
+
+/// Domain specific equality of two values.
+///
+/// The comparators are called in order at every compared pair of values, the first one that
+/// does not answer `Comparison::Recurse` decides.
+pub trait ValueComparator {
+    fn compare(&self, path: &Path, val0: &Value, val1: &Value) -> Comparison;
+}
+
+impl<F> ValueComparator for F
+where
+    F: Fn(&Path, &Value, &Value) -> Comparison,
+{
+    fn compare(&self, path: &Path, val0: &Value, val1: &Value) -> Comparison {
+        self(path, val0, val1)
+    }
+}
+
+pub(crate) fn compare_all(
+    comparators: &[Box<dyn ValueComparator>],
+    path: &Path,
+    val0: &Value,
+    val1: &Value,
+) -> Comparison {
+    comparators
+        .iter()
+        .map(|comparator| comparator.compare(path, val0, val1))
+        .find(|comparison| *comparison != Comparison::Recurse)
+        .unwrap_or(Comparison::Recurse)
+}
@@ -0,0 +1,382 @@
+//! The left json with the changes interleaved, as printed by the `diff` command:
+//!
+//! ```text
+//!   {
+//! -     bar: 1
+//! +     bar: 2
+//!       foo: [
+//!           1
+//! +         2
+//!       ]
+//!   }
+//! ```
+//!
+//! Every line starts with its diff op, ` ` for a kept line, `-` for a removed one, `+` for an
+//! added one and `~` for a note about the change, e.g. a rename.
+
+use crate::formatters::{arr_rows, left_root, obj_rows, visible_rows, DiffFormatter, Row};
+use crate::{DiffChange, JsonDiff, Path};
+use serde_json::Value;
+
+const INDENT_SIZE: usize = 4;
+
+fn format_json_val<F>(
+    jval: &Value,
+    key: Option<String>,
+    indent_count: usize,
+    diff_op: Option<&str>,
+    output: &mut F,
+) where
+    F: FnMut(&str, &str),
+{
+    let diff_op_s = diff_op.unwrap_or(" ");
+    let prefix = if let Some(key) = key {
+        format!("{}{}{}: ", diff_op_s, " ".repeat(indent_count), key)
+    } else {
+        format!("{}{}", diff_op_s, " ".repeat(indent_count))
+    };
+    match jval {
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {
+            output(diff_op_s, &format!("{}{}", prefix, jval))
+        }
+        Value::Array(arr) => {
+            let left_bracket = format!("{}{}", prefix, "[");
+            output(diff_op_s, &left_bracket);
+            arr.iter().for_each(|v| {
+                format_json_val(v, None, indent_count + INDENT_SIZE, diff_op, output)
+            });
+            let right_bracket = format!("{}{}{}", diff_op_s, " ".repeat(indent_count), "]");
+            output(diff_op_s, &right_bracket)
+        }
+        Value::Object(vmap) => {
+            let left_brace = format!("{}{}", prefix, "{");
+            output(diff_op_s, &left_brace);
+            for (key, val) in vmap {
+                format_json_val(
+                    val,
+                    Some(key.to_owned()),
+                    indent_count + INDENT_SIZE,
+                    diff_op,
+                    output,
+                );
+            }
+            let right_brace = format!("{}{}{}", diff_op_s, " ".repeat(indent_count), "}");
+            output(diff_op_s, &right_brace);
+        }
+    };
+}
+
+fn format_collapsed<F>(
+    val: &Value,
+    key: Option<String>,
+    changes: usize,
+    indent_count: usize,
+    output: &mut F,
+) where
+    F: FnMut(&str, &str),
+{
+    let (open, close) = if val.is_array() {
+        ("[", "]")
+    } else {
+        ("{", "}")
+    };
+    let summary = format!(
+        "~{}{}{} … {} change{} {}",
+        " ".repeat(indent_count),
+        key.map(|key| format!("{}: ", key)).unwrap_or_default(),
+        open,
+        changes,
+        if changes == 1 { "" } else { "s" },
+        close
+    );
+    output("~", &summary);
+}
+
+fn format_added_keys<F>(
+    curr_path: &Path,
+    anchor: Option<&str>,
+    json_diff: &JsonDiff,
+    indent_count: usize,
+    output: &mut F,
+) where
+    F: FnMut(&str, &str),
+{
+    for key in json_diff.get_add_keys_after(curr_path, anchor) {
+        let path = curr_path.clone_then_add_key(key);
+        match json_diff.get_diffchange(&path).unwrap() {
+            DiffChange::Add(new_val) => format_json_val(
+                new_val,
+                Some(key.to_string()),
+                indent_count + INDENT_SIZE,
+                Some("+"),
+                output,
+            ),
+            DiffChange::Move { from } => {
+                let moved = format!(
+                    "~{}{}: (moved from {})",
+                    " ".repeat(indent_count + INDENT_SIZE),
+                    key,
+                    from
+                );
+                output("~", &moved);
+            }
+            DiffChange::Copy { from } => {
+                let copied = format!(
+                    "~{}{}: (copied from {})",
+                    " ".repeat(indent_count + INDENT_SIZE),
+                    key,
+                    from
+                );
+                output("~", &copied);
+            }
+            _ => unreachable!("not an added key"),
+        }
+    }
+}
+
+fn format_arr_change<F>(diff_change: &DiffChange, indent_count: usize, output: &mut F)
+where
+    F: FnMut(&str, &str),
+{
+    match diff_change {
+        DiffChange::Replace { old_val, new_val } => {
+            format_json_val(old_val, None, indent_count, Some("-"), output);
+            format_json_val(new_val, None, indent_count, Some("+"), output);
+        }
+        DiffChange::Collapsed {
+            old_val, changes, ..
+        } => format_collapsed(old_val, None, *changes, indent_count, output),
+        DiffChange::Add(val) => format_json_val(val, None, indent_count, Some("+"), output),
+        DiffChange::Remove(val) => format_json_val(val, None, indent_count, Some("-"), output),
+        DiffChange::KeyOrder { .. }
+        | DiffChange::Rename { .. }
+        | DiffChange::Move { .. }
+        | DiffChange::Copy { .. } => unreachable!("not an array change"),
+    }
+}
+
+/// Render the rows of an object or array. With `context`, only the changed rows and `context`
+/// rows around them are shown, the other rows are summarized.
+fn format_rows<F>(
+    rows: &[Row],
+    curr_path: &Path,
+    unit: &str,
+    json_diff: &JsonDiff,
+    indent_count: usize,
+    context: Option<usize>,
+    output: &mut F,
+) where
+    F: FnMut(&str, &str),
+{
+    let visible = visible_rows(rows, json_diff, context);
+    let mut hidden = 0;
+    for (idx, row) in rows.iter().enumerate() {
+        if !visible[idx] {
+            hidden += 1;
+            continue;
+        }
+        format_hidden(hidden, unit, indent_count + INDENT_SIZE, output);
+        hidden = 0;
+        match row {
+            Row::Kept(val, path) => format_json_loop(
+                val,
+                path,
+                json_diff,
+                indent_count + INDENT_SIZE,
+                context,
+                output,
+            ),
+            Row::AddedKeys(anchor) => {
+                format_added_keys(curr_path, *anchor, json_diff, indent_count, output)
+            }
+            Row::ArrChange(change) => format_arr_change(change, indent_count + INDENT_SIZE, output),
+        }
+    }
+    format_hidden(hidden, unit, indent_count + INDENT_SIZE, output);
+}
+
+fn format_hidden<F>(hidden: usize, unit: &str, indent_count: usize, output: &mut F)
+where
+    F: FnMut(&str, &str),
+{
+    if hidden > 0 {
+        let plural = if hidden == 1 { "" } else { "s" };
+        let line = format!(
+            " {}… {} unchanged {}{}",
+            " ".repeat(indent_count),
+            hidden,
+            unit,
+            plural
+        );
+        output(" ", &line);
+    }
+}
+
+fn format_json_loop<F>(
+    jval: &Value,
+    curr_path: &Path,
+    json_diff: &JsonDiff,
+    indent_count: usize,
+    context: Option<usize>,
+    output: &mut F,
+) where
+    F: FnMut(&str, &str),
+{
+    let key = curr_path.current_key();
+    let indent_key = if let Some(s) = &key {
+        // e.g. for path /a/b/c
+        // ______c:
+        format!(r#"{}{}: "#, " ".repeat(indent_count), s)
+    } else {
+        // for arr index path or empty path, just indent space
+        " ".repeat(indent_count)
+    };
+
+    if let Some(to) = json_diff.get_move_target(curr_path) {
+        let moved = format!("~{}(moved to {})", indent_key, to);
+        output("~", &moved);
+        return;
+    }
+
+    let from_key = json_diff.get_rename_source(curr_path);
+    if let Some(from_key) = from_key {
+        let rename = format!(
+            "~{}(renamed) {} -> {}",
+            " ".repeat(indent_count),
+            from_key,
+            key.as_deref().unwrap_or_default()
+        );
+        output("~", &rename);
+    }
+
+    if let Some(diff_change) = json_diff.get_diffchange(curr_path) {
+        match diff_change {
+            DiffChange::Remove(val) => format_json_val(val, key, indent_count, Some("-"), output),
+            DiffChange::Replace { old_val, new_val } => {
+                // the old value is shown with the old key of a renamed key
+                let old_key = from_key.cloned().or_else(|| key.to_owned());
+                format_json_val(old_val, old_key, indent_count, Some("-"), output);
+                format_json_val(new_val, key, indent_count, Some("+"), output);
+            }
+            DiffChange::Add(val) => format_json_val(val, key, indent_count, Some("+"), output),
+            DiffChange::Collapsed {
+                old_val, changes, ..
+            } => format_collapsed(old_val, key, *changes, indent_count, output),
+            DiffChange::KeyOrder { .. }
+            | DiffChange::Rename { .. }
+            | DiffChange::Move { .. }
+            | DiffChange::Copy { .. } => unreachable!(),
+        }
+        return;
+    }
+
+    // an unchanged object or array shown as context is summarized in one line
+    let unchanged_len = match jval {
+        Value::Object(vmap) if context.is_some() && !json_diff.is_changed(curr_path) => {
+            Some((vmap.len(), "{", "key", "}"))
+        }
+        Value::Array(arr) if context.is_some() && !json_diff.is_changed(curr_path) => {
+            Some((arr.len(), "[", "item", "]"))
+        }
+        _ => None,
+    };
+    if let Some((len, open, unit, close)) = unchanged_len.filter(|(len, ..)| *len > 0) {
+        let plural = if len == 1 { "" } else { "s" };
+        let line = format!(
+            " {}{} … {} unchanged {}{} {}",
+            indent_key, open, len, unit, plural, close
+        );
+        output(" ", &line);
+        return;
+    }
+
+    match jval {
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {
+            format_json_val(jval, key, indent_count, None, output)
+        }
+        Value::Object(vmap) => {
+            let left_brace = format!("{}{}{}", " ", indent_key, "{");
+            output(" ", &left_brace);
+
+            if let Some(DiffChange::KeyOrder { old, new }) =
+                json_diff.get_key_order_change(curr_path)
+            {
+                let key_order = format!(
+                    "~{}(key order) {} -> {}",
+                    " ".repeat(indent_count + INDENT_SIZE),
+                    Value::from(old.clone()),
+                    Value::from(new.clone())
+                );
+                output("~", &key_order);
+            }
+
+            let rows = obj_rows(vmap, curr_path, json_diff);
+            format_rows(
+                &rows,
+                curr_path,
+                "key",
+                json_diff,
+                indent_count,
+                context,
+                output,
+            );
+
+            let right_brace = format!("{}{}{}", " ", " ".repeat(indent_count), "}");
+            output(" ", &right_brace)
+        }
+        Value::Array(arr) => {
+            let left_bracket = format!("{}{}{}", " ", indent_key, "[");
+            output(" ", &left_bracket);
+
+            let rows = arr_rows(arr, curr_path, json_diff);
+            format_rows(
+                &rows,
+                curr_path,
+                "item",
+                json_diff,
+                indent_count,
+                context,
+                output,
+            );
+
+            let right_bracket = format!("{}{}{}", " ", " ".repeat(indent_count), "]");
+            output(" ", &right_bracket);
+        }
+    }
+}
+
+/// Renders the left json with the changes interleaved, one line per value.
+#[derive(Default)]
+pub struct ConsoleFormatter {
+    /// only show the changes and this many unchanged keys or items around them
+    pub context: Option<usize>,
+}
+
+impl ConsoleFormatter {
+    /// Render line by line, `output` is called with the diff op and the text of every line,
+    /// e.g. to color the lines.
+    pub fn format_lines<F>(&self, left: &Value, json_diff: &JsonDiff, output: &mut F)
+    where
+        F: FnMut(&str, &str),
+    {
+        format_json_loop(
+            left_root(left, json_diff),
+            json_diff.root(),
+            json_diff,
+            1,
+            self.context,
+            output,
+        );
+    }
+}
+
+impl DiffFormatter for ConsoleFormatter {
+    fn format(&self, left: &Value, json_diff: &JsonDiff) -> String {
+        let mut res = String::new();
+        self.format_lines(left, json_diff, &mut |_, line| {
+            res.push_str(line);
+            res.push('\n');
+        });
+        res
+    }
+}
@@ -0,0 +1,284 @@
+//! A Graphviz graph of the merged tree of both documents, for slides and docs:
+//!
+//! ```text
+//! jsondiffpath-cli diff --format dot left.json right.json | dot -Tsvg > diff.svg
+//! ```
+//!
+//! Every value is a node, colored by how it changed. Unchanged objects and arrays are folded
+//! into one node and a moved or copied value is linked to its source by a dashed edge.
+
+use crate::formatters::{arr_rows, left_root, obj_rows, DiffFormatter, Row};
+use crate::{DiffChange, JsonDiff, Path};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Values longer than this are cut off in the labels.
+const MAX_LABEL_LEN: usize = 40;
+
+/// Renders the diff as a Graphviz graph in the DOT language.
+#[derive(Default)]
+pub struct DotFormatter;
+
+#[derive(Clone, Copy)]
+enum NodeKind {
+    Unchanged,
+    /// an unchanged object or array, folded into one node
+    Folded,
+    /// an object or array with changes inside
+    Changed,
+    Added,
+    Removed,
+    Replaced,
+    /// an object or array with changes below `max_depth`
+    Collapsed,
+    Moved,
+}
+
+impl NodeKind {
+    fn attrs(&self) -> &'static str {
+        match self {
+            NodeKind::Unchanged => "",
+            NodeKind::Folded => r##", fillcolor="#eeeeee", fontcolor="#666666""##,
+            NodeKind::Changed => r##", penwidth=2"##,
+            NodeKind::Added => r##", fillcolor="#bbffbb""##,
+            NodeKind::Removed => r##", fillcolor="#ffbbbb""##,
+            NodeKind::Replaced => r##", fillcolor="#ffffbb""##,
+            NodeKind::Collapsed => r##", fillcolor="#ffddaa""##,
+            NodeKind::Moved => r##", fillcolor="#bbddff""##,
+        }
+    }
+}
+
+/// Escape text for a quoted DOT string.
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn truncate(text: &str) -> String {
+    match text.char_indices().nth(MAX_LABEL_LEN) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_owned(),
+    }
+}
+
+/// A scalar as it is, an object or array by its brackets.
+fn value_label(val: &Value) -> String {
+    match val {
+        Value::Object(_) => "{}".to_owned(),
+        Value::Array(_) => "[]".to_owned(),
+        val => truncate(&val.to_string()),
+    }
+}
+
+struct Graph<'a> {
+    json_diff: &'a JsonDiff,
+    lines: Vec<String>,
+    nodes: usize,
+    // the node of a path, to link moved and copied values to their source
+    path_nodes: HashMap<Path, String>,
+}
+
+impl<'a> Graph<'a> {
+    fn node(&mut self, label: &str, kind: NodeKind, parent: Option<&str>) -> String {
+        let id = format!("n{}", self.nodes);
+        self.nodes += 1;
+        self.lines.push(format!(
+            "    {} [label=\"{}\"{}];",
+            id,
+            escape_dot(label),
+            kind.attrs()
+        ));
+        if let Some(parent) = parent {
+            self.lines.push(format!("    {} -> {};", parent, id));
+        }
+        id
+    }
+
+    /// A node for `val` and all the values below it.
+    fn subtree(&mut self, val: &Value, key: &str, kind: NodeKind, parent: Option<&str>) -> String {
+        let id = self.node(&format!("{}{}", key, value_label(val)), kind, parent);
+        match val {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    self.subtree(v, &format!("{}: ", k), kind, Some(&id));
+                }
+            }
+            Value::Array(arr) => {
+                for v in arr {
+                    self.subtree(v, "", kind, Some(&id));
+                }
+            }
+            _ => {}
+        }
+        id
+    }
+
+    fn change(
+        &mut self,
+        change: &DiffChange,
+        key: &str,
+        path: Option<&Path>,
+        parent: Option<&str>,
+    ) {
+        let id = match change {
+            DiffChange::Add(val) => self.subtree(val, key, NodeKind::Added, parent),
+            DiffChange::Remove(val) => self.subtree(val, key, NodeKind::Removed, parent),
+            DiffChange::Replace { old_val, new_val } => {
+                let label = format!(
+                    "{}{} → {}",
+                    key,
+                    truncate(&old_val.to_string()),
+                    truncate(&new_val.to_string())
+                );
+                self.node(&label, NodeKind::Replaced, parent)
+            }
+            DiffChange::Collapsed {
+                old_val, changes, ..
+            } => {
+                let (open, close) = if old_val.is_array() {
+                    ("[", "]")
+                } else {
+                    ("{", "}")
+                };
+                let plural = if *changes == 1 { "" } else { "s" };
+                let label = format!("{}{} … {} change{} {}", key, open, changes, plural, close);
+                self.node(&label, NodeKind::Collapsed, parent)
+            }
+            DiffChange::Move { .. } => {
+                self.node(&format!("{}(moved)", key), NodeKind::Moved, parent)
+            }
+            DiffChange::Copy { .. } => {
+                self.node(&format!("{}(copied)", key), NodeKind::Moved, parent)
+            }
+            DiffChange::KeyOrder { .. } | DiffChange::Rename { .. } => {
+                unreachable!("not a change of a value")
+            }
+        };
+        if let Some(path) = path {
+            self.path_nodes.insert(path.clone(), id);
+        }
+    }
+
+    fn value(&mut self, val: &Value, curr_path: &Path, parent: Option<&str>) {
+        let json_diff = self.json_diff;
+        let key = curr_path
+            .current_key()
+            .map(|key| format!("{}: ", key))
+            .unwrap_or_default();
+        if json_diff.get_move_target(curr_path).is_some() {
+            let id = self.node(&format!("{}(moved away)", key), NodeKind::Moved, parent);
+            self.path_nodes.insert(curr_path.clone(), id);
+            return;
+        }
+
+        let key = match json_diff.get_rename_source(curr_path) {
+            Some(from_key) => format!("{} → {}", from_key, key),
+            None => key,
+        };
+        if let Some(change) = json_diff.get_diffchange(curr_path) {
+            self.change(change, &key, Some(curr_path), parent);
+            return;
+        }
+
+        if !json_diff.is_changed(curr_path) || !(val.is_object() || val.is_array()) {
+            let folded = |open: &str, len: usize, unit: &str, close: &str| {
+                let plural = if len == 1 { "" } else { "s" };
+                let label = format!("{}{} … {} {}{} {}", key, open, len, unit, plural, close);
+                (label, NodeKind::Folded)
+            };
+            let (label, kind) = match val {
+                Value::Object(map) => folded("{", map.len(), "key", "}"),
+                Value::Array(arr) => folded("[", arr.len(), "item", "]"),
+                val => (format!("{}{}", key, value_label(val)), NodeKind::Unchanged),
+            };
+            let id = self.node(&label, kind, parent);
+            self.path_nodes.insert(curr_path.clone(), id);
+            return;
+        }
+
+        let id = self.node(
+            &format!("{}{}", key, value_label(val)),
+            NodeKind::Changed,
+            parent,
+        );
+        self.path_nodes.insert(curr_path.clone(), id.clone());
+        let rows = match val {
+            Value::Object(map) => obj_rows(map, curr_path, json_diff),
+            Value::Array(arr) => arr_rows(arr, curr_path, json_diff),
+            _ => unreachable!("not a container"),
+        };
+        for row in rows {
+            match row {
+                Row::Kept(val, path) => self.value(val, &path, Some(&id)),
+                Row::AddedKeys(anchor) => {
+                    for key in json_diff.get_add_keys_after(curr_path, anchor) {
+                        let path = curr_path.clone_then_add_key(key);
+                        let change = json_diff.get_diffchange(&path).unwrap();
+                        self.change(change, &format!("{}: ", key), Some(&path), Some(&id));
+                    }
+                }
+                Row::ArrChange(change) => self.change(change, "", None, Some(&id)),
+            }
+        }
+    }
+
+    /// The node of `path`, or of the closest ancestor that has one when it is folded.
+    fn path_node(&self, path: &Path) -> Option<&String> {
+        let mut path = path.clone();
+        loop {
+            if let Some(id) = self.path_nodes.get(&path) {
+                return Some(id);
+            }
+            path.pop()?;
+        }
+    }
+
+    /// Dashed edges from the sources of the moved and copied values.
+    fn source_edges(&mut self) {
+        let mut edges = vec![];
+        for diff in self.json_diff.diffs() {
+            let (from, label) = match &diff.diff {
+                DiffChange::Move { from } => (from, "moved"),
+                DiffChange::Copy { from } => (from, "copied"),
+                _ => continue,
+            };
+            if let (Some(from_id), Some(to_id)) =
+                (self.path_node(from), self.path_nodes.get(&diff.path))
+            {
+                edges.push(format!(
+                    "    {} -> {} [style=dashed, constraint=false, label=\"{}\"];",
+                    from_id, to_id, label
+                ));
+            }
+        }
+        self.lines.extend(edges);
+    }
+}
+
+impl DiffFormatter for DotFormatter {
+    fn format(&self, left: &Value, json_diff: &JsonDiff) -> String {
+        let mut graph = Graph {
+            json_diff,
+            lines: vec![],
+            nodes: 0,
+            path_nodes: HashMap::new(),
+        };
+        graph.value(left_root(left, json_diff), json_diff.root(), None);
+        graph.source_edges();
+        format!(
+            "digraph jsondiff {{\n    node [shape=box, style=\"rounded,filled\", \
+             fillcolor=white, fontname=\"monospace\"];\n{}\n}}\n",
+            graph.lines.join("\n")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatters::dot::escape_dot;
+
+    #[test]
+    fn test_escape_dot() {
+        assert_eq!(escape_dot(r#"say "hi" \o/"#), r#"say \"hi\" \\o/"#);
+    }
+}
@@ -0,0 +1,241 @@
+//! A self-contained html page of the left json with the changes interleaved, in the spirit of
+//! the html formatter of jsondiffpatch: every value is an item of a nested list, removed values
+//! are struck through and added values are highlighted.
+
+use crate::formatters::{arr_rows, left_root, obj_rows, DiffFormatter, Row};
+use crate::{DiffChange, JsonDiff, Path};
+use serde_json::Value;
+
+const STYLE: &str = r#"body { font-family: sans-serif; }
+.jsondiff, .jsondiff ul { list-style: none; margin: 0; padding-left: 1.5em; }
+.jsondiff li { margin: 2px 0; }
+.jsondiff pre { display: inline-block; margin: 0; padding: 1px 4px; font-family: monospace; vertical-align: top; }
+.jsondiff details { display: inline-block; vertical-align: top; }
+.jsondiff summary { display: inline; cursor: pointer; color: #888; font-family: monospace; }
+.jsondiff-key { font-family: monospace; font-weight: bold; margin-right: 4px; }
+.jsondiff-key::after { content: ":"; }
+.jsondiff-bracket { font-family: monospace; }
+.jsondiff-note { font-family: monospace; color: #8a6d00; }
+.jsondiff-added > pre, .jsondiff-new { background: #bbffbb; }
+.jsondiff-deleted > pre, .jsondiff-old { background: #ffbbbb; text-decoration: line-through; }
+.jsondiff-renamed del { background: #ffbbbb; }
+.jsondiff-renamed ins { background: #bbffbb; text-decoration: none; }
+.jsondiff-moved, .jsondiff-copied, .jsondiff-collapsed { background: #ffffbb; }
+"#;
+
+/// Renders the diff as an html page with inline css, so it can be attached or mailed as is.
+#[derive(Default)]
+pub struct HtmlFormatter {
+    /// fold the unchanged objects and arrays, they are expanded by a click
+    pub collapse_unchanged: bool,
+}
+
+/// Escape text for html content and attribute values.
+pub fn escape_html(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#39;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn value_html(val: &Value, class: Option<&str>) -> String {
+    let json = serde_json::to_string_pretty(val).expect("a json value is serializable");
+    match class {
+        Some(class) => format!("<pre class=\"{}\">{}</pre>", class, escape_html(&json)),
+        None => format!("<pre>{}</pre>", escape_html(&json)),
+    }
+}
+
+fn key_html(key: Option<&str>) -> String {
+    key.map(|key| format!("<span class=\"jsondiff-key\">{}</span>", escape_html(key)))
+        .unwrap_or_default()
+}
+
+fn note_html(note: &str) -> String {
+    format!("<span class=\"jsondiff-note\">{}</span>", escape_html(note))
+}
+
+fn item(class: &str, key: &str, content: &str, output: &mut String) {
+    output.push_str(&format!(
+        "<li class=\"jsondiff-{}\">{}{}</li>\n",
+        class, key, content
+    ));
+}
+
+fn collapsed_note(val: &Value, changes: usize) -> String {
+    let (open, close) = if val.is_array() {
+        ("[", "]")
+    } else {
+        ("{", "}")
+    };
+    let plural = if changes == 1 { "" } else { "s" };
+    note_html(&format!(
+        "{} … {} change{} {}",
+        open, changes, plural, close
+    ))
+}
+
+impl HtmlFormatter {
+    fn format_change(&self, change: &DiffChange, key: &str, output: &mut String) {
+        match change {
+            DiffChange::Add(val) => item("added", key, &value_html(val, None), output),
+            DiffChange::Remove(val) => item("deleted", key, &value_html(val, None), output),
+            DiffChange::Replace { old_val, new_val } => {
+                let content = format!(
+                    "{} {}",
+                    value_html(old_val, Some("jsondiff-old")),
+                    value_html(new_val, Some("jsondiff-new"))
+                );
+                item("modified", key, &content, output);
+            }
+            DiffChange::Collapsed {
+                old_val, changes, ..
+            } => item("collapsed", key, &collapsed_note(old_val, *changes), output),
+            DiffChange::Move { from } => {
+                let note = note_html(&format!("(moved from {})", from));
+                item("moved", key, &note, output);
+            }
+            DiffChange::Copy { from } => {
+                let note = note_html(&format!("(copied from {})", from));
+                item("copied", key, &note, output);
+            }
+            DiffChange::KeyOrder { .. } | DiffChange::Rename { .. } => {
+                unreachable!("not a change of a value")
+            }
+        }
+    }
+
+    fn format_node(
+        &self,
+        val: &Value,
+        curr_path: &Path,
+        json_diff: &JsonDiff,
+        output: &mut String,
+    ) {
+        let key = curr_path.current_key();
+        if let Some(to) = json_diff.get_move_target(curr_path) {
+            let note = note_html(&format!("(moved to {})", to));
+            item("moved", &key_html(key.as_deref()), &note, output);
+            return;
+        }
+
+        let key = match json_diff.get_rename_source(curr_path) {
+            Some(from_key) => format!(
+                "<span class=\"jsondiff-key jsondiff-renamed\"><del>{}</del> <ins>{}</ins></span>",
+                escape_html(from_key),
+                escape_html(key.as_deref().unwrap_or_default())
+            ),
+            None => key_html(key.as_deref()),
+        };
+        if let Some(change) = json_diff.get_diffchange(curr_path) {
+            self.format_change(change, &key, output);
+            return;
+        }
+
+        // a scalar here is unchanged, or renamed only
+        if !json_diff.is_changed(curr_path) || !(val.is_object() || val.is_array()) {
+            let content = match val {
+                Value::Object(_) | Value::Array(_) if self.collapse_unchanged => {
+                    let (open, len, unit, close) = match val {
+                        Value::Array(arr) => ("[", arr.len(), "item", "]"),
+                        Value::Object(map) => ("{", map.len(), "key", "}"),
+                        _ => unreachable!(),
+                    };
+                    let plural = if len == 1 { "" } else { "s" };
+                    format!(
+                        "<details><summary>{} … {} {}{} {}</summary>{}</details>",
+                        open,
+                        len,
+                        unit,
+                        plural,
+                        close,
+                        value_html(val, None)
+                    )
+                }
+                _ => value_html(val, None),
+            };
+            item("unchanged", &key, &content, output);
+            return;
+        }
+
+        let rows = match val {
+            Value::Object(map) => obj_rows(map, curr_path, json_diff),
+            Value::Array(arr) => arr_rows(arr, curr_path, json_diff),
+            _ => unreachable!("not a container"),
+        };
+
+        let (open, close) = if val.is_array() {
+            ("[", "]")
+        } else {
+            ("{", "}")
+        };
+        output.push_str(&format!(
+            "<li class=\"jsondiff-node\">{}<span class=\"jsondiff-bracket\">{}</span>\n<ul>\n",
+            key, open
+        ));
+        if let Some(DiffChange::KeyOrder { old, new }) = json_diff.get_key_order_change(curr_path) {
+            let note = note_html(&format!(
+                "(key order) {} -> {}",
+                Value::from(old.clone()),
+                Value::from(new.clone())
+            ));
+            item("key-order", "", &note, output);
+        }
+        for row in rows {
+            match row {
+                Row::Kept(val, path) => self.format_node(val, &path, json_diff, output),
+                Row::AddedKeys(anchor) => {
+                    for key in json_diff.get_add_keys_after(curr_path, anchor) {
+                        let path = curr_path.clone_then_add_key(key);
+                        let change = json_diff.get_diffchange(&path).unwrap();
+                        self.format_change(change, &key_html(Some(key)), output);
+                    }
+                }
+                Row::ArrChange(change) => self.format_change(change, "", output),
+            }
+        }
+        output.push_str(&format!(
+            "</ul>\n<span class=\"jsondiff-bracket\">{}</span></li>\n",
+            close
+        ));
+    }
+}
+
+impl DiffFormatter for HtmlFormatter {
+    fn format(&self, left: &Value, json_diff: &JsonDiff) -> String {
+        let mut body = String::new();
+        self.format_node(
+            left_root(left, json_diff),
+            json_diff.root(),
+            json_diff,
+            &mut body,
+        );
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>json diff</title>\n\
+             <style>\n{}</style>\n</head>\n<body>\n<ul class=\"jsondiff\">\n{}</ul>\n</body>\n</html>\n",
+            STYLE, body
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatters::html::escape_html;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">'&'</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("plain"), "plain");
+    }
+}
@@ -0,0 +1,158 @@
+//! One line per change with the file, line and column of the changed value, for editors and
+//! CI annotations:
+//!
+//! ```text
+//! right.json:3:10: /name: changed from "widget" to "gadget"
+//! left.json:5:5: /price: removed 10.5
+//! ```
+//!
+//! A removed value is reported in the left file, every other change in the right file.
+
+use crate::formatters::{pointer, DiffFormatter};
+use crate::span::{SourceMap, Span};
+use crate::{DiffChange, DiffElem, JsonDiff};
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// Values longer than this are cut off in the messages.
+const MAX_VALUE_LEN: usize = 60;
+
+/// How a location is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocationStyle {
+    /// `file:line:col: message`, as compilers write it
+    Plain,
+    /// a `::warning` workflow command of GitHub Actions
+    GitHub,
+}
+
+/// Renders every change with its location in the left or the right source. The paths of the
+/// diff must start from the root of the sources, a `diff_at` needs `PathMode::Absolute`.
+pub struct LocationFormatter {
+    left_file: String,
+    left_map: SourceMap,
+    right_file: String,
+    right_map: SourceMap,
+    style: LocationStyle,
+}
+
+fn value_text(val: &Value) -> String {
+    let text = val.to_string();
+    match text.char_indices().nth(MAX_VALUE_LEN) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text,
+    }
+}
+
+/// What changed, for people to read.
+fn message(diff: &DiffElem) -> String {
+    let path = pointer(&diff.path);
+    match &diff.diff {
+        DiffChange::Replace { old_val, new_val } => format!(
+            "{}: changed from {} to {}",
+            path,
+            value_text(old_val),
+            value_text(new_val)
+        ),
+        DiffChange::Add(val) => format!("{}: added {}", path, value_text(val)),
+        DiffChange::Remove(val) => format!("{}: removed {}", path, value_text(val)),
+        DiffChange::KeyOrder { old, new } => format!(
+            "{}: key order changed from {} to {}",
+            path,
+            Value::from(old.clone()),
+            Value::from(new.clone())
+        ),
+        DiffChange::Rename { from_key } => format!("{}: renamed from {}", path, from_key),
+        DiffChange::Move { from } => format!("{}: moved from {}", path, pointer(from)),
+        DiffChange::Copy { from } => format!("{}: copied from {}", path, pointer(from)),
+        DiffChange::Collapsed { changes, .. } => format!(
+            "{}: {} change{}",
+            path,
+            changes,
+            if *changes == 1 { "" } else { "s" }
+        ),
+    }
+}
+
+/// Escape the data of a workflow command, `property` for the values of its properties.
+fn escape_github(s: &str, property: bool) -> String {
+    let s = s
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+    if property {
+        s.replace(':', "%3A").replace(',', "%2C")
+    } else {
+        s
+    }
+}
+
+impl LocationFormatter {
+    /// Find the values in the json texts `left_src` of `left_file` and `right_src` of
+    /// `right_file`, the documents that were diffed.
+    pub fn new(
+        left_file: &str,
+        left_src: &str,
+        right_file: &str,
+        right_src: &str,
+        style: LocationStyle,
+    ) -> Result<Self> {
+        Ok(Self {
+            left_file: left_file.to_owned(),
+            left_map: SourceMap::parse(left_src)
+                .with_context(|| format!("can not locate the values of {}", left_file))?,
+            right_file: right_file.to_owned(),
+            right_map: SourceMap::parse(right_src)
+                .with_context(|| format!("can not locate the values of {}", right_file))?,
+            style,
+        })
+    }
+
+    fn line(&self, file: &str, span: Option<&Span>, message: &str) -> String {
+        match (self.style, span) {
+            (LocationStyle::Plain, Some(span)) => {
+                format!("{}:{}:{}: {}", file, span.line, span.col, message)
+            }
+            (LocationStyle::Plain, None) => format!("{}: {}", file, message),
+            (LocationStyle::GitHub, Some(span)) => format!(
+                "::warning file={},line={},col={}::{}",
+                escape_github(file, true),
+                span.line,
+                span.col,
+                escape_github(message, false)
+            ),
+            (LocationStyle::GitHub, None) => format!(
+                "::warning file={}::{}",
+                escape_github(file, true),
+                escape_github(message, false)
+            ),
+        }
+    }
+}
+
+impl DiffFormatter for LocationFormatter {
+    fn format(&self, _left: &Value, json_diff: &JsonDiff) -> String {
+        let mut res = String::new();
+        let locations = json_diff.locations(&self.left_map, &self.right_map);
+        for (diff, location) in json_diff.diffs().iter().zip(locations) {
+            let (file, span) = match &diff.diff {
+                DiffChange::Remove(_) => (&self.left_file, location.left),
+                _ => (&self.right_file, location.right),
+            };
+            res.push_str(&self.line(file, span.as_ref(), &message(diff)));
+            res.push('\n');
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatters::locations::escape_github;
+
+    #[test]
+    fn test_escape_github() {
+        assert_eq!(escape_github("a,b:c%\n", true), "a%2Cb%3Ac%25%0A");
+        assert_eq!(escape_github("a,b:c%\n", false), "a,b:c%25%0A");
+    }
+}
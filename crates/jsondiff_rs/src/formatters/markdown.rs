@@ -0,0 +1,186 @@
+//! A markdown report for review comments: a table of the changes, one row per change with its
+//! json pointer, and optionally the tree output in a ```` ```diff ```` block below it.
+
+use crate::formatters::{pointer, ConsoleFormatter, DiffFormatter};
+use crate::{DiffChange, JsonDiff};
+use serde_json::Value;
+
+/// Renders the changes as a markdown table of `path | change | old | new`.
+pub struct MarkdownFormatter {
+    /// values longer than this many characters are cut off with `…`
+    pub max_value_len: usize,
+    /// show at most this many changes in the table, the rest are counted below it
+    pub max_rows: Option<usize>,
+    /// add the tree output in a fenced diff block
+    pub diff_block: bool,
+    /// only show the changes and this many unchanged keys or items around them in the diff
+    /// block
+    pub context: Option<usize>,
+}
+
+impl Default for MarkdownFormatter {
+    fn default() -> Self {
+        Self {
+            max_value_len: 80,
+            max_rows: None,
+            diff_block: false,
+            context: None,
+        }
+    }
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or_default()
+}
+
+/// Inline code that holds `text` whatever backticks it contains, with the pipes and line
+/// breaks escaped so it does not split a table cell or row.
+fn code(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let text = text
+        .replace('|', "\\|")
+        .replace('\r', "\\r")
+        .replace('\n', "\\n");
+    let ticks = "`".repeat(longest_backtick_run(&text) + 1);
+    let pad = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+    format!("{}{}{}{}{}", ticks, pad, text, pad, ticks)
+}
+
+/// Cut `text` off after `max_len` characters.
+fn truncate(text: &str, max_len: usize) -> String {
+    match text.char_indices().nth(max_len) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_owned(),
+    }
+}
+
+impl MarkdownFormatter {
+    fn value(&self, val: &Value) -> String {
+        code(&truncate(&val.to_string(), self.max_value_len))
+    }
+
+    /// The change, old and new cells of a row.
+    fn cells(&self, change: &DiffChange) -> (String, String, String) {
+        match change {
+            DiffChange::Replace { old_val, new_val } => (
+                "replaced".to_owned(),
+                self.value(old_val),
+                self.value(new_val),
+            ),
+            DiffChange::Add(val) => ("added".to_owned(), String::new(), self.value(val)),
+            DiffChange::Remove(val) => ("removed".to_owned(), self.value(val), String::new()),
+            DiffChange::KeyOrder { old, new } => (
+                "key order".to_owned(),
+                self.value(&Value::from(old.clone())),
+                self.value(&Value::from(new.clone())),
+            ),
+            DiffChange::Rename { from_key } => {
+                ("renamed".to_owned(), code(from_key), String::new())
+            }
+            DiffChange::Move { from } => ("moved".to_owned(), code(&pointer(from)), String::new()),
+            DiffChange::Copy { from } => ("copied".to_owned(), code(&pointer(from)), String::new()),
+            DiffChange::Collapsed {
+                old_val,
+                new_val,
+                changes,
+            } => (
+                format!("{} change{}", changes, if *changes == 1 { "" } else { "s" }),
+                self.value(old_val),
+                self.value(new_val),
+            ),
+        }
+    }
+}
+
+impl DiffFormatter for MarkdownFormatter {
+    fn format(&self, left: &Value, json_diff: &JsonDiff) -> String {
+        let diffs = json_diff.diffs();
+        if diffs.is_empty() {
+            return "No changes.\n".to_owned();
+        }
+
+        let mut res = String::new();
+        res.push_str("| path | change | old | new |\n");
+        res.push_str("| --- | --- | --- | --- |\n");
+        let shown = self.max_rows.unwrap_or(diffs.len()).min(diffs.len());
+        for diff in &diffs[..shown] {
+            let (change, old, new) = self.cells(&diff.diff);
+            res.push_str(&format!(
+                "| {} | {} | {} | {} |\n",
+                code(&pointer(&diff.path)),
+                change,
+                old,
+                new
+            ));
+        }
+        let hidden = diffs.len() - shown;
+        if hidden > 0 {
+            let plural = if hidden == 1 { "" } else { "s" };
+            res.push_str(&format!("\n… and {} more change{}\n", hidden, plural));
+        }
+
+        if self.diff_block {
+            let tree = ConsoleFormatter {
+                context: self.context,
+            }
+            .format(left, json_diff);
+            // a fence longer than any backtick run in the json
+            let fence = "`".repeat(longest_backtick_run(&tree).max(2) + 1);
+            res.push_str(&format!("\n{}diff\n{}{}\n", fence, tree, fence));
+        }
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatters::markdown::{code, truncate, MarkdownFormatter};
+    use crate::formatters::DiffFormatter;
+    use crate::{DiffOptions, JsonDiff};
+    use serde_json::json;
+
+    #[test]
+    fn test_code() {
+        assert_eq!(code("\"a\""), "`\"a\"`");
+        assert_eq!(code("a|b"), "`a\\|b`");
+        assert_eq!(code("a`b"), "``a`b``");
+        assert_eq!(code("`a"), "`` `a ``");
+        assert_eq!(code(""), "");
+        assert_eq!(code("a\nb\r\n"), "`a\\nb\\r\\n`");
+        assert_eq!(code("a\n`"), "`` a\\n` ``");
+    }
+
+    #[test]
+    fn test_keys_stay_in_their_row() {
+        let left = json!({"a\n`b|": {"x": 1, "y": 2}});
+        let right = json!({"c": {"x": 1, "y": 2}});
+        let opts = DiffOptions {
+            rename_threshold: Some(1.0),
+            ..Default::default()
+        };
+        let json_diff = JsonDiff::diff_with_options(&left, &right, &opts);
+        let output = MarkdownFormatter::default().format(&left, &json_diff);
+        assert_eq!(
+            output,
+            "| path | change | old | new |\n\
+             | --- | --- | --- | --- |\n\
+             | `/c` | renamed | ``a\\n`b\\|`` |  |\n"
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abcdef", 3), "abc…");
+        assert_eq!(truncate("abc", 3), "abc");
+        assert_eq!(truncate("äöüß", 2), "äö…");
+    }
+}
@@ -0,0 +1,177 @@
+//! Render a `JsonDiff` for people to read.
+//!
+//! A formatter walks the left document and shows the changes of the diff where they happen,
+//! e.g. `ConsoleFormatter` prints the left json with the removed lines marked by `-` and the
+//! added lines by `+`, `HtmlFormatter` renders a page for a browser, `DotFormatter` a graph and
+//! `MarkdownFormatter` a table of the changes.
+
+pub mod console;
+pub mod dot;
+pub mod html;
+pub mod locations;
+pub mod markdown;
+pub mod table;
+
+pub use console::ConsoleFormatter;
+pub use dot::DotFormatter;
+pub use html::HtmlFormatter;
+pub use locations::{LocationFormatter, LocationStyle};
+pub use markdown::MarkdownFormatter;
+pub use table::RecordTable;
+
+use crate::{DiffChange, JsonDiff, Path};
+use serde_json::Value;
+
+/// Renders the diff of two json documents.
+pub trait DiffFormatter {
+    /// Render `json_diff` of `left` and another json. `left` is the document the paths of the
+    /// diff start from, i.e. the whole document for a diff with `PathMode::Absolute`.
+    fn format(&self, left: &Value, json_diff: &JsonDiff) -> String;
+}
+
+/// A kept key or element, or a change, of the object or array that is rendered.
+pub(crate) enum Row<'a> {
+    /// a key or element of the left json, with its path in the diff
+    Kept(&'a Value, Path),
+    /// the keys added after a kept key, or in front of all kept keys
+    AddedKeys(Option<&'a str>),
+    /// a change of an array element
+    ArrChange(&'a DiffChange),
+}
+
+/// The rows of an object, the added keys follow the kept key they follow in the right json.
+pub(crate) fn obj_rows<'a>(
+    map: &'a serde_json::Map<String, Value>,
+    curr_path: &Path,
+    json_diff: &JsonDiff,
+) -> Vec<Row<'a>> {
+    let has_added_keys =
+        |anchor: Option<&str>| !json_diff.get_add_keys_after(curr_path, anchor).is_empty();
+    let mut rows = vec![];
+    // added keys in front of all the kept keys
+    if has_added_keys(None) {
+        rows.push(Row::AddedKeys(None));
+    }
+    for (key, val) in map {
+        // a renamed key is shown with its new key and the changes below it
+        let new_path = match json_diff.get_rename_target(&curr_path.clone_then_add_key(key)) {
+            Some(new_key) => curr_path.clone_then_add_key(new_key),
+            None => curr_path.clone_then_add_key(key),
+        };
+        rows.push(Row::Kept(val, new_path));
+        // added keys that follow this key in the right json
+        if has_added_keys(Some(key)) {
+            rows.push(Row::AddedKeys(Some(key)));
+        }
+    }
+    rows
+}
+
+/// The rows of an array in the order of the right json, the changes are interleaved with the
+/// kept elements. When `arr` is not the array the diff was made from, the changes that do not
+/// fit it are listed after its elements.
+pub(crate) fn arr_rows<'a>(
+    arr: &'a [Value],
+    curr_path: &Path,
+    json_diff: &'a JsonDiff,
+) -> Vec<Row<'a>> {
+    let mut rows = vec![];
+    let arr_changes = json_diff
+        .get_arr_changes(curr_path)
+        .map(|changes| changes.as_slice())
+        .unwrap_or_default();
+    let mut old_idx: usize = 0;
+    let mut new_idx: usize = 0;
+    let mut new_len = arr.len();
+    let mut diffchange_idx: usize = 0;
+    // when adding to the end of the arr
+    while new_idx < new_len || diffchange_idx < arr_changes.len() {
+        let diff_change = arr_changes
+            .get(diffchange_idx)
+            .filter(|diff_change| diff_change.path.arr_idx() == Some(new_idx));
+        match diff_change.map(|diff_change| &diff_change.diff) {
+            Some(change @ (DiffChange::Replace { .. } | DiffChange::Collapsed { .. })) => {
+                rows.push(Row::ArrChange(change));
+                diffchange_idx += 1;
+                old_idx += 1;
+                new_idx += 1;
+            }
+            Some(change @ DiffChange::Add(_)) => {
+                rows.push(Row::ArrChange(change));
+                new_idx += 1;
+                new_len += 1;
+                diffchange_idx += 1;
+            }
+            Some(DiffChange::Remove(_)) => {
+                // removals of consecutive values share the same index
+                while diffchange_idx < arr_changes.len()
+                    && arr_changes[diffchange_idx].diff.is_remove()
+                    && arr_changes[diffchange_idx].path.arr_idx() == Some(new_idx)
+                {
+                    rows.push(Row::ArrChange(&arr_changes[diffchange_idx].diff));
+                    old_idx += 1;
+                    new_len = new_len.saturating_sub(1);
+                    diffchange_idx += 1;
+                }
+            }
+            Some(_) => unreachable!("not an array change"),
+            None => match arr.get(old_idx) {
+                Some(val) => {
+                    rows.push(Row::Kept(val, curr_path.clone_then_add_idx(new_idx)));
+                    old_idx += 1;
+                    new_idx += 1;
+                }
+                None => {
+                    rows.extend(
+                        arr_changes[diffchange_idx..]
+                            .iter()
+                            .map(|change| Row::ArrChange(&change.diff)),
+                    );
+                    break;
+                }
+            },
+        }
+    }
+    rows
+}
+
+/// Which rows to show: with `context`, only the changed rows and `context` rows around them,
+/// all rows otherwise.
+pub(crate) fn visible_rows(
+    rows: &[Row],
+    json_diff: &JsonDiff,
+    context: Option<usize>,
+) -> Vec<bool> {
+    let changed: Vec<bool> = rows
+        .iter()
+        .map(|row| match row {
+            Row::Kept(_, path) => json_diff.is_changed(path),
+            Row::AddedKeys(_) | Row::ArrChange(_) => true,
+        })
+        .collect();
+    (0..rows.len())
+        .map(|idx| match context {
+            None => true,
+            Some(context) => {
+                let start = idx.saturating_sub(context);
+                let end = (idx + context + 1).min(rows.len());
+                changed[start..end].iter().any(|changed| *changed)
+            }
+        })
+        .collect()
+}
+
+/// The json pointer of `path` for people to read.
+pub(crate) fn pointer(path: &Path) -> String {
+    if path.is_empty() {
+        "(root)".to_owned()
+    } else {
+        path.to_json_pointer().to_escaped_string()
+    }
+}
+
+/// The left value at the root of the diff, or `left` itself when the root is not in it, e.g.
+/// when `left` is already the value at the root.
+pub(crate) fn left_root<'a>(left: &'a Value, json_diff: &JsonDiff) -> &'a Value {
+    json_diff.root().locate(left).unwrap_or(left)
+}
@@ -0,0 +1,370 @@
+//! A table of two arrays of records, e.g. rows exported from a database. The records of both
+//! arrays are paired by the value at a json pointer, like a primary key, and every column is a
+//! field of the records:
+//!
+//! ```text
+//!   id  name   price
+//! ~ 1   apple  1.5 → 1.75
+//! - 2   pear   2
+//! + 3   plum   3
+//! ```
+
+use anyhow::{bail, Context, Result};
+use jsonptr::pointer::JsonPointer;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// How a record changed from the left array to the right array.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordChange {
+    Kept,
+    Added,
+    Removed,
+    Changed,
+}
+
+impl RecordChange {
+    fn diff_op(&self) -> &'static str {
+        match self {
+            RecordChange::Kept => " ",
+            RecordChange::Added => "+",
+            RecordChange::Removed => "-",
+            RecordChange::Changed => "~",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            RecordChange::Kept => "kept",
+            RecordChange::Added => "added",
+            RecordChange::Removed => "removed",
+            RecordChange::Changed => "changed",
+        }
+    }
+}
+
+enum Cell {
+    Same(String),
+    Changed { old: String, new: String },
+}
+
+impl Cell {
+    fn text(&self) -> String {
+        match self {
+            Cell::Same(text) => text.to_owned(),
+            Cell::Changed { old, new } => format!("{} → {}", old, new),
+        }
+    }
+}
+
+struct RecordRow {
+    change: RecordChange,
+    cells: Vec<Cell>,
+}
+
+/// The records of two arrays paired by a key, in the order of the right array with the removed
+/// records at their place in the left array.
+pub struct RecordTable {
+    columns: Vec<String>,
+    rows: Vec<RecordRow>,
+}
+
+/// A field as it is shown in a cell, strings without quotes and a missing field as nothing.
+fn cell_text(val: Option<&Value>) -> String {
+    match val {
+        None => String::new(),
+        Some(Value::String(s)) => s.to_owned(),
+        Some(val) => val.to_string(),
+    }
+}
+
+/// The records of `arr` by the value at `key`.
+fn index_records<'a>(
+    arr: &'a Value,
+    key: &JsonPointer,
+    side: &str,
+) -> Result<Vec<(String, &'a serde_json::Map<String, Value>)>> {
+    let arr = arr
+        .as_array()
+        .with_context(|| format!("the {} json is not an array", side))?;
+    let mut records = vec![];
+    let mut seen = HashMap::new();
+    for (idx, record) in arr.iter().enumerate() {
+        let map = record
+            .as_object()
+            .with_context(|| format!("item {} of the {} json is not an object", idx, side))?;
+        let key_val = key.get(record).with_context(|| {
+            format!(
+                "item {} of the {} json has no '{}'",
+                idx,
+                side,
+                key.to_escaped_string()
+            )
+        })?;
+        let key_str = key_val.to_string();
+        if let Some(first) = seen.insert(key_str.clone(), idx) {
+            bail!(
+                "items {} and {} of the {} json have the same key {}",
+                first,
+                idx,
+                side,
+                key_str
+            );
+        }
+        records.push((key_str, map));
+    }
+    Ok(records)
+}
+
+impl RecordTable {
+    /// Pair the records of the arrays `left` and `right` by their value at `key`. Every record
+    /// must be an object with a unique key.
+    pub fn compare(left: &Value, right: &Value, key: &JsonPointer) -> Result<Self> {
+        let left_records = index_records(left, key, "left")?;
+        let right_records = index_records(right, key, "right")?;
+
+        let mut columns: Vec<String> = vec![];
+        for (_, record) in left_records.iter().chain(right_records.iter()) {
+            for field in record.keys() {
+                if !columns.contains(field) {
+                    columns.push(field.to_owned());
+                }
+            }
+        }
+
+        let left_keys: HashMap<&str, usize> = left_records
+            .iter()
+            .enumerate()
+            .map(|(idx, (key, _))| (key.as_str(), idx))
+            .collect();
+        let right_keys: HashMap<&str, usize> = right_records
+            .iter()
+            .enumerate()
+            .map(|(idx, (key, _))| (key.as_str(), idx))
+            .collect();
+        let row = |change: RecordChange, record: &serde_json::Map<String, Value>| RecordRow {
+            change,
+            cells: columns
+                .iter()
+                .map(|column| Cell::Same(cell_text(record.get(column))))
+                .collect(),
+        };
+        let removed = |idx: usize| {
+            let (key, record) = &left_records[idx];
+            (!right_keys.contains_key(key.as_str())).then(|| row(RecordChange::Removed, record))
+        };
+
+        let mut rows = vec![];
+        // the next left record that is not shown yet
+        let mut left_idx = 0;
+        for (key, right_record) in right_records.iter() {
+            let matched = match left_keys.get(key.as_str()) {
+                Some(matched) => *matched,
+                None => {
+                    rows.push(row(RecordChange::Added, right_record));
+                    continue;
+                }
+            };
+            // the removed records in front of the pair
+            if matched >= left_idx {
+                rows.extend((left_idx..matched).filter_map(removed));
+                left_idx = matched + 1;
+            }
+            let left_record = left_records[matched].1;
+            let cells: Vec<Cell> = columns
+                .iter()
+                .map(|column| {
+                    let (old, new) = (left_record.get(column), right_record.get(column));
+                    if old == new {
+                        Cell::Same(cell_text(old))
+                    } else {
+                        Cell::Changed {
+                            old: cell_text(old),
+                            new: cell_text(new),
+                        }
+                    }
+                })
+                .collect();
+            let change = if cells.iter().all(|cell| matches!(cell, Cell::Same(_))) {
+                RecordChange::Kept
+            } else {
+                RecordChange::Changed
+            };
+            rows.push(RecordRow { change, cells });
+        }
+        rows.extend((left_idx..left_records.len()).filter_map(removed));
+
+        Ok(Self { columns, rows })
+    }
+
+    /// The fields of the records, in the order they first appear in the left and then the
+    /// right array.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Number of the added, removed and changed records.
+    pub fn changed_records(&self) -> usize {
+        self.rows
+            .iter()
+            .filter(|row| row.change != RecordChange::Kept)
+            .count()
+    }
+
+    /// Whether the records with the same key in both arrays are equal, and no record was added
+    /// or removed. The order of the records is not compared.
+    pub fn is_unchanged(&self) -> bool {
+        self.rows.iter().all(|row| row.change == RecordChange::Kept)
+    }
+
+    /// A column-aligned table with the diff op of every row in front. `style` is called with
+    /// `+` and `-` and the text of every added and removed row and with `~` and the text of
+    /// every changed cell, e.g. to color them.
+    pub fn format_text<F>(&self, style: F) -> String
+    where
+        F: Fn(&str, &str) -> String,
+    {
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(col, column)| {
+                self.rows
+                    .iter()
+                    .map(|row| row.cells[col].text().chars().count())
+                    .chain([column.chars().count()])
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let pad = |text: &str, col: usize| {
+            if col + 1 == widths.len() {
+                text.to_owned()
+            } else {
+                let fill = widths[col].saturating_sub(text.chars().count());
+                format!("{}{}", text, " ".repeat(fill))
+            }
+        };
+
+        let mut res = String::new();
+        let header: Vec<String> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(col, column)| pad(column, col))
+            .collect();
+        res.push_str(format!("  {}", header.join("  ")).trim_end());
+        res.push('\n');
+        for row in self.rows.iter() {
+            let cells: Vec<String> = row
+                .cells
+                .iter()
+                .enumerate()
+                .map(|(col, cell)| match cell {
+                    Cell::Changed { .. } => style("~", &pad(&cell.text(), col)),
+                    Cell::Same(text) => pad(text, col),
+                })
+                .collect();
+            let diff_op = row.change.diff_op();
+            let line = format!("{} {}", diff_op, cells.join("  "));
+            let line = line.trim_end();
+            match row.change {
+                RecordChange::Added | RecordChange::Removed => res.push_str(&style(diff_op, line)),
+                RecordChange::Kept | RecordChange::Changed => res.push_str(line),
+            }
+            res.push('\n');
+        }
+        res
+    }
+
+    /// The same table as csv, the first column is how the record changed and a changed field
+    /// is `old → new`.
+    pub fn to_csv(&self) -> String {
+        let mut res = String::new();
+        let header = ["change".to_owned()]
+            .into_iter()
+            .chain(self.columns.iter().cloned());
+        res.push_str(&csv_line(header));
+        for row in self.rows.iter() {
+            let fields = [row.change.name().to_owned()]
+                .into_iter()
+                .chain(row.cells.iter().map(|cell| cell.text()));
+            res.push_str(&csv_line(fields));
+        }
+        res
+    }
+}
+
+/// A line of csv (RFC 4180), fields with a separator, a quote or a line break are quoted.
+fn csv_line<I: Iterator<Item = String>>(fields: I) -> String {
+    let fields: Vec<String> = fields
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatters::table::{csv_line, RecordTable};
+    use jsonptr::pointer::JsonPointer;
+    use serde_json::json;
+
+    fn table(left: serde_json::Value, right: serde_json::Value) -> RecordTable {
+        RecordTable::compare(&left, &right, &JsonPointer::new("/id").unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_record_table() {
+        let left = json!([
+            {"id": 1, "name": "apple"},
+            {"id": 2, "name": "pear"},
+            {"id": 3, "name": "plum"},
+        ]);
+        let right = json!([
+            {"id": 1, "name": "apple"},
+            {"id": 3, "name": "prune"},
+            {"id": 4, "name": "fig"},
+        ]);
+        let text = table(left, right).format_text(|diff_op, text| format!("{}[{}]", diff_op, text));
+        assert_eq!(
+            text,
+            "  id  name\n  1   apple\n-[- 2   pear]\n~ 3   ~[plum → prune]\n+[+ 4   fig]\n"
+        );
+    }
+
+    #[test]
+    fn test_record_table_order() {
+        // removed records stay at their place in the left array
+        let left = json!([{"id": 1}, {"id": 2}, {"id": 3}, {"id": 4}]);
+        let right = json!([{"id": 3}, {"id": 1}, {"id": 5}]);
+        let csv = table(left, right).to_csv();
+        assert_eq!(
+            csv,
+            "change,id\r\nremoved,2\r\nkept,3\r\nkept,1\r\nadded,5\r\nremoved,4\r\n"
+        );
+    }
+
+    #[test]
+    fn test_record_table_errors() {
+        let key = JsonPointer::new("/id").unwrap();
+        assert!(RecordTable::compare(&json!({}), &json!([]), &key).is_err());
+        assert!(RecordTable::compare(&json!([1]), &json!([]), &key).is_err());
+        assert!(RecordTable::compare(&json!([{"name": 1}]), &json!([]), &key).is_err());
+        assert!(RecordTable::compare(&json!([{"id": 1}, {"id": 1}]), &json!([]), &key).is_err());
+    }
+
+    #[test]
+    fn test_csv_line() {
+        let fields = ["a", "b,c", "say \"hi\""].map(|s| s.to_owned());
+        assert_eq!(
+            csv_line(fields.into_iter()),
+            "a,\"b,c\",\"say \"\"hi\"\"\"\r\n"
+        );
+    }
+}
@@ -1,10 +1,17 @@
 pub fn lcs<T: PartialEq>(arr0: &[T], arr1: &[T]) -> Vec<(usize, usize)> {
-    let len0 = arr0.len();
-    let len1 = arr1.len();
+    lcs_by(arr0.len(), arr1.len(), |i, j| arr0[i] == arr1[j])
+}
+
+/// Longest common subsequence of two sequences of length `len0` and `len1`, where the
+/// elements at `i` and `j` are the same when `same(i, j)` says so.
+pub fn lcs_by<F>(len0: usize, len1: usize, same: F) -> Vec<(usize, usize)>
+where
+    F: Fn(usize, usize) -> bool,
+{
     let mut dp = vec![vec![0; len1 + 1]; len0 + 1];
-    for (i, v0) in arr0.iter().enumerate() {
-        for (j, v1) in arr1.iter().enumerate() {
-            if v0 == v1 {
+    for i in 0..len0 {
+        for j in 0..len1 {
+            if same(i, j) {
                 dp[i + 1][j + 1] = dp[i][j] + 1;
             } else {
                 dp[i + 1][j + 1] = dp[i + 1][j].max(dp[i][j + 1]);
@@ -21,7 +28,7 @@ pub fn lcs<T: PartialEq>(arr0: &[T], arr1: &[T]) -> Vec<(usize, usize)> {
         } else if dp[i][j] == dp[i][j - 1] {
             j -= 1;
         } else {
-            assert!(arr0[i - 1] == arr1[j - 1]);
+            debug_assert!(same(i - 1, j - 1));
             res.push((i - 1, j - 1));
             i -= 1;
             j -= 1;
@@ -32,9 +39,59 @@ pub fn lcs<T: PartialEq>(arr0: &[T], arr1: &[T]) -> Vec<(usize, usize)> {
     res
 }
 
+/// Heaviest common subsequence: the pairs `(i, j)`, increasing in both `i` and `j`, with the
+/// greatest total score, where `score(i, j)` is `None` for elements that can not be paired.
+pub fn align_by<F>(len0: usize, len1: usize, score: F) -> Vec<(usize, usize)>
+where
+    F: Fn(usize, usize) -> Option<f64>,
+{
+    #[derive(Clone, Copy)]
+    enum Step {
+        Skip0,
+        Skip1,
+        Pair,
+    }
+
+    let mut dp = vec![vec![0.0; len1 + 1]; len0 + 1];
+    let mut steps = vec![vec![Step::Skip0; len1 + 1]; len0 + 1];
+    for i in 0..len0 {
+        for j in 0..len1 {
+            let (mut best, mut step) = (dp[i][j + 1], Step::Skip0);
+            if dp[i + 1][j] > best {
+                (best, step) = (dp[i + 1][j], Step::Skip1);
+            }
+            if let Some(s) = score(i, j) {
+                if dp[i][j] + s > best {
+                    (best, step) = (dp[i][j] + s, Step::Pair);
+                }
+            }
+            dp[i + 1][j + 1] = best;
+            steps[i + 1][j + 1] = step;
+        }
+    }
+
+    let mut i = len0;
+    let mut j = len1;
+    let mut res = vec![];
+    while i > 0 && j > 0 {
+        match steps[i][j] {
+            Step::Skip0 => i -= 1,
+            Step::Skip1 => j -= 1,
+            Step::Pair => {
+                res.push((i - 1, j - 1));
+                i -= 1;
+                j -= 1;
+            }
+        }
+    }
+
+    res.reverse();
+    res
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::lcs::lcs;
+    use crate::lcs::{align_by, lcs};
 
     #[test]
     fn test_lcs() {
@@ -104,6 +161,27 @@ mod tests {
         assert_eq!(lcs_vec, vec![(0, 1), (1, 2), (4, 4), (5, 6)]);
     }
 
+    #[test]
+    fn test_align_by() {
+        // numbers pair when they differ by at most 1, closer is better
+        let arr0: [i32; 4] = [1, 5, 10, 20];
+        let arr1 = [2, 10, 21, 30];
+        let res = align_by(arr0.len(), arr1.len(), |i, j| {
+            let d = (arr0[i] - arr1[j]).abs();
+            (d <= 1).then(|| 1.0 - d as f64 / 2.0)
+        });
+        assert_eq!(res, vec![(0, 0), (2, 1), (3, 2)]);
+
+        // a single better pair wins over two weak ones
+        let res = align_by(2, 2, |i, j| match (i, j) {
+            (0, 1) => Some(1.0),
+            (1, 0) => Some(0.3),
+            _ => None,
+        });
+        assert_eq!(res, vec![(0, 1)]);
+        assert!(align_by(0, 3, |_, _| Some(1.0)).is_empty());
+    }
+
     #[test]
     fn test_lcs_common_pre_suf() {
         let s1 = "abctotodef";
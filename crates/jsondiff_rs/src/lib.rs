@@ -1,31 +1,36 @@
+pub mod comparator;
+pub mod formatters;
 mod lcs;
-// pub mod patch;
-
-use anyhow::Result;
+mod moves;
+pub mod normalizer;
+pub mod patch;
+pub mod similarity;
+pub mod span;
+pub mod tree_edit;
+
+use anyhow::{Context, Result};
+use comparator::{Comparison, ValueComparator};
+use jsonpatch::JsonPatch;
+use jsonptr::pointer::JsonPointer;
+use normalizer::Normalizer;
 use serde_json::map::Map;
 use serde_json::Value;
+use span::{SourceMap, Span};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::convert::From;
 use std::fmt::Display;
-use std::fs::File;
-use std::io::BufReader;
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::string::ToString;
+use tree_edit::TreeEditCosts;
 
 fn read_json_str(s: &str) -> Result<Value> {
     let v: Value = serde_json::from_str(s)?;
     Ok(v)
 }
 
-fn read_json_file<P: AsRef<std::path::Path>>(path: P) -> Result<Value> {
-    let f = File::open(path)?;
-    let reader = BufReader::new(f);
-    let v = serde_json::from_reader(reader)?;
-    Ok(v)
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Hash)]
 pub enum PathElem {
     Key(String),
@@ -87,7 +92,7 @@ impl Path {
     }
 
     pub fn parent_path(&self) -> Option<Self> {
-        if self.len() == 0 {
+        if self.is_empty() {
             None
         } else {
             let mut path = self.clone();
@@ -150,12 +155,69 @@ impl Path {
             None
         }
     }
+
+    pub fn to_json_pointer(&self) -> JsonPointer {
+        let mut json_ptr = JsonPointer::new("").unwrap();
+        for elem in self.iter() {
+            match elem {
+                PathElem::Key(key) => json_ptr.push(key),
+                PathElem::Index(idx) => json_ptr.push(idx),
+            }
+        }
+        json_ptr
+    }
+
+    /// The path of the value that `json_ptr` references in `root`, the reference tokens of
+    /// arrays become indices.
+    pub fn from_json_pointer(json_ptr: &JsonPointer, root: &Value) -> Result<Self> {
+        // the pointer is valid in `root` from here on
+        json_ptr.get(root)?;
+        let mut path = Self::empty();
+        let mut cur = root;
+        for token in json_ptr.iter() {
+            let key = token.as_key();
+            cur = match cur {
+                Value::Array(arr) => {
+                    let idx: usize = key.parse()?;
+                    path.push_idx(idx);
+                    &arr[idx]
+                }
+                _ => {
+                    path.push_key(key);
+                    &cur[key]
+                }
+            };
+        }
+        Ok(path)
+    }
+
+    /// Return the value that the path locates in `root`, if any.
+    pub fn locate<'a>(&self, root: &'a Value) -> Option<&'a Value> {
+        let mut cur = root;
+        for elem in self.iter() {
+            cur = match (elem, cur) {
+                (PathElem::Key(key), Value::Object(map)) => map.get(key)?,
+                (PathElem::Index(idx), Value::Array(arr)) => arr.get(*idx)?,
+                _ => return None,
+            };
+        }
+        Some(cur)
+    }
+}
+
+/// Where a change is in the left and the right source, see `JsonDiff::locate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffLocation {
+    pub left: Option<Span>,
+    pub right: Option<Span>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct DiffElem {
     pub diff: DiffChange,
     pub path: Path,
+    /// Where the change is in the left and the right source, set by `JsonDiff::locate`.
+    pub location: Option<DiffLocation>,
 }
 
 impl Display for DiffElem {
@@ -177,6 +239,33 @@ impl Display for DiffElem {
                 diff_jmap.insert("new_val".to_owned(), new_val.clone());
                 diff_jmap.insert("diff".to_owned(), Value::String("replace".to_owned()));
             }
+            DiffChange::KeyOrder { old, new } => {
+                diff_jmap.insert("old_keys".to_owned(), Value::from(old.clone()));
+                diff_jmap.insert("new_keys".to_owned(), Value::from(new.clone()));
+                diff_jmap.insert("diff".to_owned(), Value::String("key_order".to_owned()));
+            }
+            DiffChange::Rename { from_key } => {
+                diff_jmap.insert("from_key".to_owned(), Value::String(from_key.clone()));
+                diff_jmap.insert("diff".to_owned(), Value::String("rename".to_owned()));
+            }
+            DiffChange::Move { from } => {
+                diff_jmap.insert("from".to_owned(), Value::String(from.to_string()));
+                diff_jmap.insert("diff".to_owned(), Value::String("move".to_owned()));
+            }
+            DiffChange::Copy { from } => {
+                diff_jmap.insert("from".to_owned(), Value::String(from.to_string()));
+                diff_jmap.insert("diff".to_owned(), Value::String("copy".to_owned()));
+            }
+            DiffChange::Collapsed {
+                old_val,
+                new_val,
+                changes,
+            } => {
+                diff_jmap.insert("old_val".to_owned(), old_val.clone());
+                diff_jmap.insert("new_val".to_owned(), new_val.clone());
+                diff_jmap.insert("changes".to_owned(), Value::from(*changes));
+                diff_jmap.insert("diff".to_owned(), Value::String("collapsed".to_owned()));
+            }
         };
         let diff_j = Value::Object(diff_jmap);
         let pretty_diff_str = serde_json::to_string_pretty(&diff_j).unwrap();
@@ -192,17 +281,43 @@ impl DiffElem {
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum DiffChange {
-    Replace { old_val: Value, new_val: Value },
+    Replace {
+        old_val: Value,
+        new_val: Value,
+    },
     Add(Value),
     Remove(Value),
+    /// The keys kept in both objects are in a different order, `old` and `new` list them in the
+    /// order of the left and the right object.
+    KeyOrder {
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    /// The key was renamed from `from_key`, the changes of its value follow as changes below
+    /// the new key.
+    Rename {
+        from_key: String,
+    },
+    /// The value was moved here from `from`, it is unchanged.
+    Move {
+        from: Path,
+    },
+    /// The value was added as a copy of the unchanged value at `from`.
+    Copy {
+        from: Path,
+    },
+    /// The value is at `DiffOptions::max_depth` and has `changes` changes below it, which are
+    /// not reported one by one.
+    Collapsed {
+        old_val: Value,
+        new_val: Value,
+        changes: usize,
+    },
 }
 
 impl DiffChange {
     pub fn is_remove(&self) -> bool {
-        match self {
-            DiffChange::Remove(_) => true,
-            _ => false,
-        }
+        matches!(self, DiffChange::Remove(_))
     }
 
     pub fn as_remove(&self) -> Option<&Value> {
@@ -213,26 +328,68 @@ impl DiffChange {
     }
 }
 
+/// The result of diffing two json documents.
+///
+/// The diff elements are kept in document order: changes and removals follow
+/// the key order of the left document, additions follow the key order of the
+/// right document and array changes follow the element order. Diffing the same
+/// two documents always yields the same elements in the same order.
 #[derive(Debug)]
 pub struct JsonDiff {
+    diffs: Vec<DiffElem>,
     path2obj_change: HashMap<Path, DiffChange>,
     child_added_keys: HashMap<Path, Vec<String>>,
+    // path of an added key -> the kept key it follows in the right document
+    added_key_anchors: HashMap<Path, Option<String>>,
+    // path of a renamed key in the left document -> the new key
+    renamed_keys: HashMap<Path, String>,
+    // path of a renamed key in the diff -> the old key, kept apart from the changes of the
+    // value that follow the rename at the same path
+    renamed_from: HashMap<Path, String>,
+    // path of a moved value in the left document -> its path in the right document
+    moved_to: HashMap<Path, Path>,
+    // paths of the changes and all their ancestors
+    changed_paths: HashSet<Path>,
     path2arr_changes: HashMap<Path, Vec<DiffElem>>,
+    path2key_order: HashMap<Path, DiffChange>,
+    // path of the diffed values in the documents
+    root: Path,
 }
 
 impl JsonDiff {
-    fn new(diffs: Vec<DiffElem>) -> Self {
+    fn new(
+        diffs: Vec<DiffElem>,
+        left: &Value,
+        right: &Value,
+        root: Path,
+        opts: &DiffOptions,
+    ) -> Self {
         let mut path2obj_change = HashMap::new();
         let mut child_added_keys = HashMap::new();
         let mut path2arr_changes = HashMap::new();
-        for diff in diffs {
-            if diff.path.is_arr_path() {
+        let mut path2key_order = HashMap::new();
+        let mut renamed_keys = HashMap::new();
+        let mut renamed_from = HashMap::new();
+        let mut moved_to = HashMap::new();
+        let mut changed_paths = HashSet::new();
+        for diff in diffs.iter().cloned() {
+            if let DiffChange::Move { from } = &diff.diff {
+                Self::insert_with_ancestors(&mut changed_paths, from.clone());
+            }
+            Self::insert_with_ancestors(&mut changed_paths, diff.path.clone());
+
+            if let DiffChange::KeyOrder { .. } = diff.diff {
+                // the object itself is kept, so do not mix it with the changes of values
+                path2key_order.insert(diff.path, diff.diff);
+            } else if diff.path.is_arr_path() {
                 let arr_changes = path2arr_changes
                     .entry(diff.path.parent_path().unwrap())
                     .or_insert_with(Vec::new);
                 arr_changes.push(diff);
             } else {
-                if let DiffChange::Add(_) = diff.diff {
+                if let DiffChange::Add(_) | DiffChange::Move { .. } | DiffChange::Copy { .. } =
+                    diff.diff
+                {
                     let last_key = diff.path.last().unwrap();
                     // just for PathElem::Key
                     if let PathElem::Key(str_key) = last_key {
@@ -241,25 +398,178 @@ impl JsonDiff {
                         keys.push(str_key.to_owned());
                     }
                 }
+                if let DiffChange::Rename { from_key } = diff.diff {
+                    let parent_path = diff.path.parent_path().unwrap();
+                    let new_key = diff.path.current_key().unwrap();
+                    renamed_keys.insert(parent_path.clone_then_add_key(&from_key), new_key);
+                    renamed_from.insert(diff.path, from_key);
+                    continue;
+                }
+                if let DiffChange::Move { from } = &diff.diff {
+                    moved_to.insert(from.clone(), diff.path.clone());
+                }
                 path2obj_change.insert(diff.path, diff.diff);
             }
         }
-        Self {
+        let mut json_diff = Self {
+            diffs,
             path2obj_change,
             child_added_keys,
+            added_key_anchors: HashMap::new(),
+            renamed_keys,
+            renamed_from,
+            moved_to,
+            changed_paths,
             path2arr_changes,
+            path2key_order,
+            root,
+        };
+        json_diff.added_key_anchors = json_diff.anchor_added_keys(left, right, opts);
+        json_diff
+    }
+
+    fn insert_with_ancestors(paths: &mut HashSet<Path>, mut path: Path) {
+        while paths.insert(path.clone()) {
+            if path.pop().is_none() {
+                break;
+            }
         }
     }
 
+    /// For every added key find the closest preceding key of the right document that also
+    /// exists in the left document, so the added key can be shown at its actual position.
+    /// The anchor is the key of the left document, which differs from the key of the right
+    /// document when the keys are only equal after normalization.
+    fn anchor_added_keys(
+        &self,
+        left: &Value,
+        right: &Value,
+        opts: &DiffOptions,
+    ) -> HashMap<Path, Option<String>> {
+        let mut anchors = HashMap::new();
+        for (parent_path, keys) in &self.child_added_keys {
+            let mut anchor: Option<String> = None;
+            if let Some(Value::Object(map)) = parent_path.locate(right) {
+                // the normalizers see the paths of the diffed values
+                let diffed_path = Path::new(parent_path[self.root.len()..].to_vec());
+                let left_keys = match self.left_path(parent_path).locate(left) {
+                    Some(Value::Object(left_map)) => opts.normalized_keys(&diffed_path, left_map),
+                    _ => HashMap::new(),
+                };
+                for key in map.keys() {
+                    let path = parent_path.clone_then_add_key(key);
+                    if keys.contains(key) {
+                        anchors.insert(path, anchor.clone());
+                    } else if let Some(from_key) = self.get_rename_source(&path) {
+                        // a renamed key is shown at the place of its old key
+                        anchor = Some(from_key.to_owned());
+                    } else {
+                        let norm_key =
+                            normalizer::normalize_key(&opts.normalizers, &diffed_path, key);
+                        anchor = Some(left_keys.get(&norm_key).map_or(key, |k| *k).to_owned());
+                    }
+                }
+            }
+        }
+        anchors
+    }
+
     pub fn diff_json(s0: &str, s1: &str) -> Result<Self> {
-        let diffs = diff_json(s0, s1)?;
-        Ok(Self::new(diffs))
+        let json0 = read_json_str(s0)?;
+        let json1 = read_json_str(s1)?;
+        Ok(Self::diff(&json0, &json1))
+    }
+
+    /// Diff the json texts `s0` and `s1` with `opts` and locate the changes in them, see
+    /// `locate`.
+    pub fn diff_sources(s0: &str, s1: &str, opts: &DiffOptions) -> Result<Self> {
+        let mut json_diff = Self::diff_with_options(&read_json_str(s0)?, &read_json_str(s1)?, opts);
+        json_diff.locate(&SourceMap::parse(s0)?, &SourceMap::parse(s1)?);
+        Ok(json_diff)
+    }
+
+    pub fn diff(json0: &Value, json1: &Value) -> Self {
+        Self::diff_with_options(json0, json1, &DiffOptions::default())
+    }
+
+    pub fn diff_with_options(json0: &Value, json1: &Value, opts: &DiffOptions) -> Self {
+        let diffs = diff_json_val(json0, json1, opts);
+        Self::new(diffs, json0, json1, Path::empty(), opts)
+    }
+
+    /// Diff only the values that `json_ptr` references in both documents.
+    pub fn diff_at(
+        json0: &Value,
+        json1: &Value,
+        json_ptr: &JsonPointer,
+        opts: &DiffOptions,
+        path_mode: PathMode,
+    ) -> Result<Self> {
+        let ptr_str = json_ptr.to_escaped_string();
+        let sub0 = json_ptr
+            .get(json0)
+            .with_context(|| format!("'{}' not found in the left json", ptr_str))?;
+        let sub1 = json_ptr
+            .get(json1)
+            .with_context(|| format!("'{}' not found in the right json", ptr_str))?;
+        let diffs = diff_json_val(sub0, sub1, opts);
+        match path_mode {
+            PathMode::Relative => Ok(Self::new(diffs, sub0, sub1, Path::empty(), opts)),
+            PathMode::Absolute => {
+                let prefix = Path::from_json_pointer(json_ptr, json0)?;
+                let prefixed = |path: &Path| Path::new([&prefix[..], &path[..]].concat());
+                let diffs = diffs
+                    .into_iter()
+                    .map(|diff| DiffElem {
+                        path: prefixed(&diff.path),
+                        diff: match diff.diff {
+                            DiffChange::Move { from } => DiffChange::Move {
+                                from: prefixed(&from),
+                            },
+                            DiffChange::Copy { from } => DiffChange::Copy {
+                                from: prefixed(&from),
+                            },
+                            change => change,
+                        },
+                        location: None,
+                    })
+                    .collect();
+                Ok(Self::new(diffs, json0, json1, prefix, opts))
+            }
+        }
     }
 
+    /// Path of the diffed values, the path of `diff_at` with `PathMode::Absolute` and empty
+    /// otherwise. The paths of the changes start from the same document as this path.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// All diff elements in document order.
+    pub fn diffs(&self) -> &[DiffElem] {
+        &self.diffs
+    }
+
+    /// Keys added under the object at `path`, in the key order of the right document.
     pub fn get_add_keys(&self, path: &Path) -> Option<&Vec<String>> {
         self.child_added_keys.get(path)
     }
 
+    /// Keys added under the object at `path` which follow the kept key `anchor` in the right
+    /// document, `anchor` is `None` for the keys placed before all kept keys.
+    pub fn get_add_keys_after(&self, path: &Path, anchor: Option<&str>) -> Vec<&String> {
+        self.get_add_keys(path)
+            .map(|keys| {
+                keys.iter()
+                    .filter(|key| {
+                        let key_anchor = self.added_key_anchors.get(&path.clone_then_add_key(key));
+                        key_anchor.map_or(anchor.is_none(), |a| a.as_deref() == anchor)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn get_diffchange(&self, path: &Path) -> Option<&DiffChange> {
         self.path2obj_change.get(path)
     }
@@ -267,11 +577,364 @@ impl JsonDiff {
     pub fn get_arr_changes(&self, path: &Path) -> Option<&Vec<DiffElem>> {
         self.path2arr_changes.get(path)
     }
+
+    /// Whether there is a change at `path` or below it.
+    pub fn is_changed(&self, path: &Path) -> bool {
+        self.changed_paths.contains(path)
+    }
+
+    /// The new key of the key at `path` of the left document, if it was renamed.
+    pub fn get_rename_target(&self, path: &Path) -> Option<&String> {
+        self.renamed_keys.get(path)
+    }
+
+    /// The old key of the key at `path` of the diff, if it was renamed.
+    pub fn get_rename_source(&self, path: &Path) -> Option<&String> {
+        self.renamed_from.get(path)
+    }
+
+    /// The path in the right document of the value at `path` of the left document, if it was
+    /// moved.
+    pub fn get_move_target(&self, path: &Path) -> Option<&Path> {
+        self.moved_to.get(path)
+    }
+
+    /// The path in the left document of the value at `path` of the diff. The paths of the diff
+    /// follow the right document: renamed keys have their new key and array indices count the
+    /// added elements.
+    pub fn left_path(&self, path: &Path) -> Path {
+        self.left_path_at(path, None)
+    }
+
+    /// `removal` is the position of a removal at `path` in the changes of its array, a run of
+    /// removals shares one index of the diff.
+    fn left_path_at(&self, path: &Path, removal: Option<usize>) -> Path {
+        let mut diff_path = Path::empty();
+        let mut left_path = Path::empty();
+        for (depth, elem) in path.iter().enumerate() {
+            match elem {
+                PathElem::Key(key) => {
+                    diff_path.push_key(key);
+                    match self.get_rename_source(&diff_path) {
+                        Some(from_key) => left_path.push_key(from_key),
+                        None => left_path.push_key(key),
+                    }
+                }
+                PathElem::Index(idx) => {
+                    let arr_changes = self
+                        .get_arr_changes(&diff_path)
+                        .map(|changes| changes.as_slice())
+                        .unwrap_or_default();
+                    let listed_before = match removal {
+                        Some(pos) if depth + 1 == path.len() => pos,
+                        _ => arr_changes.len(),
+                    };
+                    let mut left_idx = *idx;
+                    for change in &arr_changes[..listed_before] {
+                        match (&change.diff, change.path.arr_idx()) {
+                            (DiffChange::Add(_), Some(i)) if i < *idx => left_idx -= 1,
+                            (DiffChange::Remove(_), Some(i)) if i <= *idx => left_idx += 1,
+                            _ => {}
+                        }
+                    }
+                    diff_path.push_idx(*idx);
+                    left_path.push_idx(left_idx);
+                }
+            }
+        }
+        left_path
+    }
+
+    /// Set the location of every change of `diffs` in the left and the right source. A
+    /// removed value only has a span in the left source and an added one in the right.
+    pub fn locate(&mut self, left: &SourceMap, right: &SourceMap) {
+        let locations = self.locations(left, right);
+        for (diff, location) in self.diffs.iter_mut().zip(locations) {
+            diff.location = Some(location);
+        }
+    }
+
+    /// The location of every change in the sources, in the order of `diffs`.
+    pub(crate) fn locations(&self, left: &SourceMap, right: &SourceMap) -> Vec<DiffLocation> {
+        // number of the changes of an array so far
+        let mut arr_positions: HashMap<Path, usize> = HashMap::new();
+        self.diffs
+            .iter()
+            .map(|diff| {
+                let mut position = None;
+                if diff.path.is_arr_path() {
+                    let counter = arr_positions
+                        .entry(diff.path.parent_path().unwrap())
+                        .or_default();
+                    position = Some(*counter);
+                    *counter += 1;
+                }
+                let left_span = match &diff.diff {
+                    DiffChange::Add(_) => None,
+                    DiffChange::Move { from } => left.get(&self.left_path(from)),
+                    // the source of a copy is a path of the left document
+                    DiffChange::Copy { from } => left.get(from),
+                    _ => left.get(&self.left_path_at(&diff.path, position)),
+                };
+                let right_span = match &diff.diff {
+                    DiffChange::Remove(_) => None,
+                    _ => right.get(&diff.path),
+                };
+                DiffLocation {
+                    left: left_span.copied(),
+                    right: right_span.copied(),
+                }
+            })
+            .collect()
+    }
+
+    /// The json patch that turns the left document into the right one.
+    pub fn to_patch(&self) -> JsonPatch {
+        patch::diffs_to_patch(&self.diffs)
+    }
+
+    /// The `DiffChange::KeyOrder` of the object at `path`, if its keys were reordered.
+    pub fn get_key_order_change(&self, path: &Path) -> Option<&DiffChange> {
+        self.path2key_order.get(path)
+    }
 }
 
-enum ArrDiffMethod {
+/// How `JsonDiff::diff_at` reports the paths of the changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathMode {
+    /// From the referenced values, the json patch applies to them.
+    Relative,
+    /// From the root of the documents, the json patch applies to the whole document.
+    Absolute,
+}
+
+/// Options to control how two json documents are compared.
+#[derive(Default)]
+pub struct DiffOptions {
+    /// How to find the changes.
+    pub engine: DiffEngine,
+    /// How to diff two arrays.
+    pub arr_diff: ArrDiffMethod,
+    /// Also report a change in the order of the keys of an object as `DiffChange::KeyOrder`.
+    /// Without the `preserve_order` feature the keys are always sorted, so nothing is reported.
+    pub key_order: bool,
+    /// Treat a missing key and a key set to `null` as equal.
+    pub null_as_missing: bool,
+    /// Treat a missing key and a key set to its default value as equal, the default value is
+    /// looked up by the same path in this document.
+    pub defaults: Option<Value>,
+    /// Judge equality on the values produced by these normalizers, they are applied in order
+    /// at every path. The diff still reports the original values.
+    pub normalizers: Vec<Box<dyn Normalizer>>,
+    /// Domain specific equality, asked at every path before the values are compared as usual.
+    pub comparators: Vec<Box<dyn ValueComparator>>,
+    /// Report a removed key and an added key of the same object as `DiffChange::Rename` when
+    /// the similarity of their values (see `similarity::leaf_similarity`) reaches this
+    /// threshold, `Some(1.0)` only pairs equal values.
+    pub rename_threshold: Option<f64>,
+    /// Report an object or array that was removed at one path and added at another path as
+    /// `DiffChange::Move`. Only paths without array indices are considered.
+    pub detect_moves: bool,
+    /// Report an added object or array that equals an unchanged value elsewhere in the
+    /// document as `DiffChange::Copy`. Only paths without array indices are considered.
+    pub detect_copies: bool,
+    /// Report the changes of an object or array at this depth (the number of keys and indices
+    /// in its path) as one `DiffChange::Collapsed`.
+    pub max_depth: Option<usize>,
+}
+
+impl DiffOptions {
+    /// Whether `val` at `path` is what a missing key stands for, so that dropping or adding
+    /// the key is not a change.
+    fn is_implicit(&self, path: &Path, val: &Value) -> bool {
+        if self.null_as_missing && val.is_null() {
+            return true;
+        }
+        match &self.defaults {
+            Some(defaults) => path.locate(defaults) == Some(val),
+            None => false,
+        }
+    }
+
+    /// Whether `val0` and `val1` at `path` are the same after normalization, as far as it can
+    /// be told at `path`. Scalars are compared by their normalized forms. Objects and arrays
+    /// are only compared here when a normalizer rewrote one of them, otherwise their values are
+    /// compared one by one below them.
+    fn same_normalized(&self, path: &Path, val0: &Value, val1: &Value) -> bool {
+        let norm0 = normalizer::normalize(&self.normalizers, path, val0);
+        let norm1 = normalizer::normalize(&self.normalizers, path, val1);
+        let is_container = |val: &Value| val.is_object() || val.is_array();
+        if !is_container(val0) && !is_container(val1) {
+            norm0 == norm1
+        } else if matches!(norm0, Cow::Owned(_)) || matches!(norm1, Cow::Owned(_)) {
+            normalizer::normalize_deep(&self.normalizers, path, val0)
+                == normalizer::normalize_deep(&self.normalizers, path, val1)
+        } else {
+            false
+        }
+    }
+
+    /// The keys of `map`, the object at `path`, by their normalized forms.
+    fn normalized_keys<'a>(
+        &self,
+        path: &Path,
+        map: &'a Map<String, Value>,
+    ) -> HashMap<Cow<'a, str>, &'a String> {
+        map.keys()
+            .map(|k| (normalizer::normalize_key(&self.normalizers, path, k), k))
+            .collect()
+    }
+
+    /// The keys of two objects at `path` with the same normalized form, left key -> right key.
+    fn pair_keys<'a>(
+        &self,
+        path: &Path,
+        m0: &'a Map<String, Value>,
+        m1: &'a Map<String, Value>,
+    ) -> HashMap<&'a String, &'a String> {
+        let keys1 = self.normalized_keys(path, m1);
+        self.normalized_keys(path, m0)
+            .into_iter()
+            .filter_map(|(norm_key, k0)| Some((k0, *keys1.get(&norm_key)?)))
+            .collect()
+    }
+
+    /// The old and the new order of the `paired` keys, by the keys of the left object, when
+    /// `key_order` is set and the order changed.
+    fn changed_key_order(
+        &self,
+        m0: &Map<String, Value>,
+        m1: &Map<String, Value>,
+        paired: &HashMap<&String, &String>,
+    ) -> Option<(Vec<String>, Vec<String>)> {
+        if !self.key_order {
+            return None;
+        }
+        let paired_rev: HashMap<&String, &String> =
+            paired.iter().map(|(k0, k1)| (*k1, *k0)).collect();
+        let old: Vec<String> = m0
+            .keys()
+            .filter(|k| paired.contains_key(k))
+            .cloned()
+            .collect();
+        let new: Vec<String> = m1
+            .keys()
+            .filter_map(|k| paired_rev.get(k).map(|k0| k0.to_string()))
+            .collect();
+        (old != new).then_some((old, new))
+    }
+
+    /// Whether there is no difference between `val0` and `val1` at `path`, the same as an
+    /// empty diff but without building it: the values are walked until the first difference.
+    fn same_value(&self, path: &Path, val0: &Value, val1: &Value) -> bool {
+        match comparator::compare_all(&self.comparators, path, val0, val1) {
+            Comparison::Equal => return true,
+            Comparison::Different => return false,
+            Comparison::Recurse => {}
+        }
+        if !self.normalizers.is_empty() && self.same_normalized(path, val0, val1) {
+            return true;
+        }
+        match (val0, val1) {
+            (Value::Object(m0), Value::Object(m1)) => {
+                let paired = self.pair_keys(path, m0, m1);
+                let paired1: HashSet<&String> = paired.values().cloned().collect();
+                // a key without a partner is a change unless it stands for a missing key
+                let kept = |k: &String, v: &Value, is_paired: bool| {
+                    is_paired || self.is_implicit(&path.clone_then_add_key(k), v)
+                };
+                self.changed_key_order(m0, m1, &paired).is_none()
+                    && m0.iter().all(|(k, v)| kept(k, v, paired.contains_key(k)))
+                    && m1.iter().all(|(k, v)| kept(k, v, paired1.contains(k)))
+                    && paired.iter().all(|(k0, k1)| {
+                        self.same_value(&path.clone_then_add_key(k1), &m0[*k0], &m1[*k1])
+                    })
+            }
+            (Value::Array(arr0), Value::Array(arr1)) => match self.arr_diff {
+                ArrDiffMethod::Simple => arr0 == arr1,
+                // the elements are aligned one to one exactly when they are all equal
+                ArrDiffMethod::Lcs | ArrDiffMethod::Fuzzy { .. } => {
+                    arr0.len() == arr1.len()
+                        && (0..arr0.len()).all(|idx| {
+                            self.same_value(&path.clone_then_add_idx(idx), &arr0[idx], &arr1[idx])
+                        })
+                }
+            },
+            (_, _) => val0 == val1,
+        }
+    }
+
+    /// Pair the removed keys with the added keys, of the same object, that have a similar
+    /// enough value. Return the old key of every renamed key.
+    fn pair_renamed_keys<'a>(
+        &self,
+        path: &Path,
+        m0: &'a Map<String, Value>,
+        m1: &'a Map<String, Value>,
+        removed: &[&'a String],
+        added: &[&'a String],
+    ) -> HashMap<&'a String, &'a String> {
+        let mut renames = HashMap::new();
+        let threshold = match self.rename_threshold {
+            Some(threshold) => threshold,
+            None => return renames,
+        };
+        let mut paired: HashSet<&String> = HashSet::new();
+        for new_key in added {
+            let v1 = &m1[*new_key];
+            let mut best: Option<(&String, f64)> = None;
+            for old_key in removed.iter().filter(|k| !paired.contains(*k)) {
+                let v0 = &m0[*old_key];
+                let score = if self.same_value(&path.clone_then_add_key(new_key), v0, v1) {
+                    1.0
+                } else {
+                    similarity::leaf_similarity(v0, v1)
+                };
+                if score > 0.0 && score >= threshold && best.is_none_or(|(_, s)| score > s) {
+                    best = Some((old_key, score));
+                }
+            }
+            if let Some((old_key, _)) = best {
+                paired.insert(old_key);
+                renames.insert(*new_key, old_key);
+            }
+        }
+        renames
+    }
+
+    fn normalize_arr(&self, path: &Path, arr: &[Value]) -> Vec<Value> {
+        arr.iter()
+            .enumerate()
+            .map(|(idx, v)| {
+                normalizer::normalize_deep(&self.normalizers, &path.clone_then_add_idx(idx), v)
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub enum DiffEngine {
+    /// Compare objects key by key and arrays with `DiffOptions::arr_diff`.
+    #[default]
+    Structural,
+    /// Follow a minimal cost tree edit (see `tree_edit::tree_edit_distance`). The values are
+    /// compared as they are: `arr_diff`, `key_order`, `null_as_missing`, `defaults`, the
+    /// normalizers, the comparators, the rename detection and `max_depth` do not apply.
+    TreeEdit(TreeEditCosts),
+}
+
+#[derive(Default)]
+pub enum ArrDiffMethod {
+    /// Replace the whole array when the two arrays are not equal.
     Simple,
+    /// Align the elements by their longest common subsequence.
+    #[default]
     Lcs,
+    /// Align the elements whose similarity (see `similarity::leaf_similarity`) reaches the
+    /// threshold, so that the changes inside the aligned elements are reported below them.
+    /// Equal elements are aligned first, and a stretch of more than 256 by 256 changed
+    /// elements between them is removed and added without scoring its elements.
+    Fuzzy { threshold: f64 },
 }
 
 impl ArrDiffMethod {
@@ -281,6 +944,7 @@ impl ArrDiffMethod {
         arr1: &[Value],
         mut diffs: Vec<DiffElem>,
         path: Path,
+        opts: &DiffOptions,
     ) -> Vec<DiffElem> {
         match *self {
             ArrDiffMethod::Simple => {
@@ -293,30 +957,52 @@ impl ArrDiffMethod {
                             new_val: Value::Array(arr1.to_vec()),
                         },
                         path,
+                        location: None,
                     });
                     diffs
                 }
             }
-            ArrDiffMethod::Lcs => diff_json_arr_lcs(arr0, arr1, diffs, path),
+            ArrDiffMethod::Lcs => diff_json_arr_lcs(arr0, arr1, diffs, path, opts),
+            ArrDiffMethod::Fuzzy { threshold } => {
+                diff_json_arr_fuzzy(arr0, arr1, threshold, diffs, path, opts)
+            }
         }
     }
 }
 
+#[cfg(test)]
 fn diff_json(json0: &str, json1: &str) -> Result<Vec<DiffElem>> {
-    diff_json_str(json0, json1, ArrDiffMethod::Lcs)
+    diff_json_str(json0, json1, &DiffOptions::default())
 }
 
+#[cfg(test)]
 fn diff_json_simple(json0: &str, json1: &str) -> Result<Vec<DiffElem>> {
-    diff_json_str(json0, json1, ArrDiffMethod::Simple)
+    let opts = DiffOptions {
+        arr_diff: ArrDiffMethod::Simple,
+        ..Default::default()
+    };
+    diff_json_str(json0, json1, &opts)
 }
 
-fn diff_json_str(json0: &str, json1: &str, arr_diff: ArrDiffMethod) -> Result<Vec<DiffElem>> {
-    let path = Path::empty();
-    let diffs = Vec::new();
+#[cfg(test)]
+fn diff_json_str(json0: &str, json1: &str, opts: &DiffOptions) -> Result<Vec<DiffElem>> {
     let json0 = read_json_str(json0)?;
     let json1 = read_json_str(json1)?;
-    let diffs = diff_json_inner(&json0, &json1, diffs, path, &arr_diff);
-    Ok(diffs)
+    Ok(diff_json_val(&json0, &json1, opts))
+}
+
+fn diff_json_val(json0: &Value, json1: &Value, opts: &DiffOptions) -> Vec<DiffElem> {
+    let mut diffs = match &opts.engine {
+        DiffEngine::Structural => diff_json_inner(json0, json1, Vec::new(), Path::empty(), opts),
+        DiffEngine::TreeEdit(costs) => tree_edit::diff(json0, json1, costs),
+    };
+    if opts.detect_moves {
+        diffs = moves::detect_moves(diffs);
+    }
+    if opts.detect_copies {
+        diffs = moves::detect_copies(diffs, json0, json1);
+    }
+    diffs
 }
 
 fn diff_json_inner(
@@ -324,16 +1010,65 @@ fn diff_json_inner(
     jval1: &Value,
     mut diffs: Vec<DiffElem>,
     path: Path,
-    arr_diff: &ArrDiffMethod,
+    opts: &DiffOptions,
 ) -> Vec<DiffElem> {
+    let both_containers = matches!(
+        (jval0, jval1),
+        (Value::Object(_), Value::Object(_)) | (Value::Array(_), Value::Array(_))
+    );
+    if opts.max_depth == Some(path.len()) && both_containers {
+        // the values below are deeper than max_depth, so they are diffed as usual
+        let changes = diff_json_subtree(jval0, jval1, Vec::new(), path.clone(), opts).len();
+        if changes > 0 {
+            diffs.push(DiffElem {
+                diff: DiffChange::Collapsed {
+                    old_val: jval0.clone(),
+                    new_val: jval1.clone(),
+                    changes,
+                },
+                path,
+                location: None,
+            });
+        }
+        return diffs;
+    }
+    diff_json_subtree(jval0, jval1, diffs, path, opts)
+}
+
+fn diff_json_subtree(
+    jval0: &Value,
+    jval1: &Value,
+    mut diffs: Vec<DiffElem>,
+    path: Path,
+    opts: &DiffOptions,
+) -> Vec<DiffElem> {
+    match comparator::compare_all(&opts.comparators, &path, jval0, jval1) {
+        Comparison::Equal => return diffs,
+        Comparison::Different => {
+            diffs.push(DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: jval0.clone(),
+                    new_val: jval1.clone(),
+                },
+                path,
+                location: None,
+            });
+            return diffs;
+        }
+        Comparison::Recurse => {}
+    }
+    if !opts.normalizers.is_empty() && opts.same_normalized(&path, jval0, jval1) {
+        return diffs;
+    }
     match (jval0, jval1) {
         (Value::Null, Value::Null) => diffs,
         (Value::Bool(b0), Value::Bool(b1)) if b0 == b1 => diffs,
         (Value::Number(n0), Value::Number(n1)) if n0 == n1 => diffs,
         (Value::String(s0), Value::String(s1)) if s0 == s1 => diffs,
-        (Value::Object(m0), Value::Object(m1)) => diff_json_map(m0, m1, diffs, path, arr_diff),
+        (Value::Object(m0), Value::Object(m1)) => diff_json_map(m0, m1, diffs, path, opts),
         (Value::Array(v0), Value::Array(v1)) => {
-            arr_diff.diff(v0.as_slice(), v1.as_slice(), diffs, path)
+            opts.arr_diff
+                .diff(v0.as_slice(), v1.as_slice(), diffs, path, opts)
         }
         (_, _) => {
             // not equal case
@@ -343,6 +1078,7 @@ fn diff_json_inner(
                     new_val: jval1.clone(),
                 },
                 path,
+                location: None,
             });
             diffs
         }
@@ -354,33 +1090,89 @@ fn diff_json_map(
     m1: &Map<String, Value>,
     mut diffs: Vec<DiffElem>,
     path: Path,
-    arr_diff: &ArrDiffMethod,
+    opts: &DiffOptions,
 ) -> Vec<DiffElem> {
-    for (k, v0) in m0.iter() {
-        if let Some(v1) = m1.get(k) {
-            let new_path = path.clone_then_add_key(k);
-            diffs = diff_json_inner(v0, v1, diffs, new_path, arr_diff);
-        }
-    }
-    let keys0: HashSet<String> = m0.keys().cloned().collect();
-    let keys1: HashSet<String> = m1.keys().cloned().collect();
-    let keys_only_in_m0 = keys0.difference(&keys1);
-    let keys_only_in_m1 = keys1.difference(&keys0);
+    let paired = opts.pair_keys(&path, m0, m1);
+    let paired_rev: HashMap<&String, &String> = paired.iter().map(|(k0, k1)| (*k1, *k0)).collect();
 
-    for k in keys_only_in_m0 {
-        let new_path = path.clone_then_add_key(k);
+    if let Some((old, new)) = opts.changed_key_order(m0, m1, &paired) {
         diffs.push(DiffElem {
-            diff: DiffChange::Remove(m0.get(k).unwrap().clone()),
-            path: new_path,
+            diff: DiffChange::KeyOrder { old, new },
+            path: path.clone(),
+            location: None,
         })
     }
 
-    for k in keys_only_in_m1 {
-        let new_path = path.clone_then_add_key(k);
-        diffs.push(DiffElem {
-            diff: DiffChange::Add(m1.get(k).unwrap().clone()),
-            path: new_path,
+    let removed: Vec<&String> = m0
+        .iter()
+        .filter(|(k, v0)| {
+            !paired.contains_key(k) && !opts.is_implicit(&path.clone_then_add_key(k), v0)
+        })
+        .map(|(k, _)| k)
+        .collect();
+    let added: Vec<&String> = m1
+        .iter()
+        .filter(|(k, v1)| {
+            !paired_rev.contains_key(k) && !opts.is_implicit(&path.clone_then_add_key(k), v1)
         })
+        .map(|(k, _)| k)
+        .collect();
+    let renames = opts.pair_renamed_keys(&path, m0, m1, &removed, &added);
+    let renamed_from: HashSet<&String> = renames.values().cloned().collect();
+    let removed: HashSet<&String> = removed.into_iter().collect();
+
+    // changes and removals in the key order of m0
+    for (k, v0) in m0.iter() {
+        let new_path = path.clone_then_add_key(k);
+        if let Some(k1) = paired.get(k) {
+            if *k1 == k {
+                diffs = diff_json_inner(v0, &m1[k], diffs, new_path, opts);
+            }
+        } else if removed.contains(k) && !renamed_from.contains(k) {
+            diffs.push(DiffElem {
+                diff: DiffChange::Remove(v0.clone()),
+                path: new_path,
+                location: None,
+            })
+        }
+    }
+
+    // additions and renames in the key order of m1. A key paired with a key that is only
+    // equal after normalization is reported as renamed when its value changed, so the change
+    // is at the key of the right document like any other rename.
+    let added: HashSet<&String> = added.into_iter().collect();
+    for k in m1.keys() {
+        let new_path = path.clone_then_add_key(k);
+        if let Some(k0) = paired_rev.get(k).filter(|k0| **k0 != k) {
+            let changes = diff_json_inner(&m0[*k0], &m1[k], Vec::new(), new_path.clone(), opts);
+            if !changes.is_empty() {
+                diffs.push(DiffElem {
+                    diff: DiffChange::Rename {
+                        from_key: k0.to_string(),
+                    },
+                    path: new_path,
+                    location: None,
+                });
+                diffs.extend(changes);
+            }
+        } else if !added.contains(k) {
+            continue;
+        } else if let Some(from_key) = renames.get(k) {
+            diffs.push(DiffElem {
+                diff: DiffChange::Rename {
+                    from_key: from_key.to_string(),
+                },
+                path: new_path.clone(),
+                location: None,
+            });
+            diffs = diff_json_inner(&m0[*from_key], &m1[k], diffs, new_path, opts);
+        } else {
+            diffs.push(DiffElem {
+                diff: DiffChange::Add(m1[k].clone()),
+                path: new_path,
+                location: None,
+            })
+        }
     }
 
     diffs
@@ -389,50 +1181,125 @@ fn diff_json_map(
 fn diff_json_arr_lcs(
     arr0: &[Value],
     arr1: &[Value],
-    mut diffs: Vec<DiffElem>,
+    diffs: Vec<DiffElem>,
     path: Path,
+    opts: &DiffOptions,
 ) -> Vec<DiffElem> {
-    let mut lcs_pairs = lcs::lcs(arr0, arr1);
+    let lcs_pairs = if !opts.comparators.is_empty() {
+        lcs::lcs_by(arr0.len(), arr1.len(), |i, j| {
+            opts.same_value(&path.clone_then_add_idx(i), &arr0[i], &arr1[j])
+        })
+    } else if opts.normalizers.is_empty() {
+        lcs::lcs(arr0, arr1)
+    } else {
+        lcs::lcs(
+            &opts.normalize_arr(&path, arr0),
+            &opts.normalize_arr(&path, arr1),
+        )
+    };
+    let diff_pair = |idx0: usize, idx1: usize, diffs, path| {
+        diff_json_inner(&arr0[idx0], &arr1[idx1], diffs, path, opts)
+    };
+    diff_json_arr_aligned(arr0, arr1, lcs_pairs, false, diffs, path, diff_pair)
+}
+
+/// The most pairs of elements between two equal elements that the fuzzy alignment scores,
+/// the elements of a larger stretch are removed and added without aligning them.
+const FUZZY_MAX_PAIRS: usize = 1 << 16;
+
+fn diff_json_arr_fuzzy(
+    arr0: &[Value],
+    arr1: &[Value],
+    threshold: f64,
+    diffs: Vec<DiffElem>,
+    path: Path,
+    opts: &DiffOptions,
+) -> Vec<DiffElem> {
+    let score = |i: usize, j: usize| {
+        let score = if opts.same_value(&path.clone_then_add_idx(i), &arr0[i], &arr1[j]) {
+            1.0
+        } else {
+            similarity::leaf_similarity(&arr0[i], &arr1[j])
+        };
+        (score > 0.0 && score >= threshold).then_some(score)
+    };
+    // the equal elements are aligned first by their hashes, then only the elements between
+    // two of them are scored against each other
+    let hashes0: Vec<u64> = arr0.iter().map(similarity::value_hash).collect();
+    let hashes1: Vec<u64> = arr1.iter().map(similarity::value_hash).collect();
+    let equal_pairs = lcs::lcs_by(arr0.len(), arr1.len(), |i, j| {
+        hashes0[i] == hashes1[j] && arr0[i] == arr1[j]
+    });
+    let mut pairs = vec![];
+    let (mut start0, mut start1) = (0, 0);
+    for (end0, end1) in equal_pairs.into_iter().chain([(arr0.len(), arr1.len())]) {
+        let (len0, len1) = (end0 - start0, end1 - start1);
+        if len0 * len1 <= FUZZY_MAX_PAIRS {
+            let window = lcs::align_by(len0, len1, |i, j| score(start0 + i, start1 + j));
+            pairs.extend(window.into_iter().map(|(i, j)| (start0 + i, start1 + j)));
+        }
+        if end0 < arr0.len() {
+            pairs.push((end0, end1));
+        }
+        (start0, start1) = (end0 + 1, end1 + 1);
+    }
+    let diff_pair = |idx0: usize, idx1: usize, diffs, path| {
+        diff_json_inner(&arr0[idx0], &arr1[idx1], diffs, path, opts)
+    };
+    diff_json_arr_aligned(arr0, arr1, pairs, true, diffs, path, diff_pair)
+}
+
+/// Diff two arrays along the aligned `pairs` of indices, `diff_pair` diffs two elements.
+/// Equal pairs are skipped unless `fuzzy`, then the pairs are diffed and the elements between
+/// two pairs are removed and added instead of being replaced one by one.
+pub(crate) fn diff_json_arr_aligned<F>(
+    arr0: &[Value],
+    arr1: &[Value],
+    mut pairs: Vec<(usize, usize)>,
+    fuzzy: bool,
+    mut diffs: Vec<DiffElem>,
+    path: Path,
+    mut diff_pair: F,
+) -> Vec<DiffElem>
+where
+    F: FnMut(usize, usize, Vec<DiffElem>, Path) -> Vec<DiffElem>,
+{
     let mut idx0 = 0;
     let mut idx1 = 0;
     let mut shift_idx = 0;
 
-    lcs_pairs.reverse();
+    pairs.reverse();
 
-    while !lcs_pairs.is_empty() {
-        let same_idx_pair = lcs_pairs.last().unwrap();
+    while !pairs.is_empty() {
+        let same_idx_pair = pairs.last().unwrap();
         assert!(idx0 <= same_idx_pair.0);
         assert!(idx1 <= same_idx_pair.1);
         if idx0 == same_idx_pair.0 && idx1 == same_idx_pair.1 {
-            // do nothing
-            lcs_pairs.pop();
+            if fuzzy {
+                let new_path = path.clone_then_add_idx(shift_idx);
+                diffs = diff_pair(idx0, idx1, diffs, new_path);
+            }
+            pairs.pop();
             shift_idx += 1;
             idx0 += 1;
             idx1 += 1;
-        } else if idx0 < same_idx_pair.0 && idx1 < same_idx_pair.1 {
+        } else if idx0 < same_idx_pair.0 && idx1 < same_idx_pair.1 && !fuzzy {
             // replace
             let new_path = path.clone_then_add_idx(shift_idx);
-            diffs = diff_json_inner(
-                &arr0[idx0],
-                &arr1[idx1],
-                diffs,
-                new_path,
-                &ArrDiffMethod::Lcs,
-            );
+            diffs = diff_pair(idx0, idx1, diffs, new_path);
             shift_idx += 1;
             idx0 += 1;
             idx1 += 1;
-        } else if idx0 < same_idx_pair.0 && idx1 == same_idx_pair.1 {
-            // remove val in arr0
-            let mut idx = shift_idx;
+        } else if idx0 < same_idx_pair.0 {
+            // remove val in arr0, all at the same index as each removal shifts the rest
             while idx0 < same_idx_pair.0 {
-                let new_path = path.clone_then_add_idx(idx);
+                let new_path = path.clone_then_add_idx(shift_idx);
                 diffs.push(DiffElem {
                     diff: DiffChange::Remove(arr0[idx0].clone()),
                     path: new_path,
+                    location: None,
                 });
                 idx0 += 1;
-                idx += 1;
             }
         } else if idx0 == same_idx_pair.0 && idx1 < same_idx_pair.1 {
             // add val in arr1
@@ -440,6 +1307,7 @@ fn diff_json_arr_lcs(
             diffs.push(DiffElem {
                 diff: DiffChange::Add(arr1[idx1].clone()),
                 path: new_path,
+                location: None,
             });
             idx1 += 1;
             shift_idx += 1;
@@ -448,16 +1316,10 @@ fn diff_json_arr_lcs(
 
     let len0 = arr0.len();
     let len1 = arr1.len();
-    while idx0 < len0 && idx1 < len1 {
+    while idx0 < len0 && idx1 < len1 && !fuzzy {
         // replace
         let new_path = path.clone_then_add_idx(shift_idx);
-        diffs = diff_json_inner(
-            &arr0[idx0],
-            &arr1[idx1],
-            diffs,
-            new_path,
-            &ArrDiffMethod::Lcs,
-        );
+        diffs = diff_pair(idx0, idx1, diffs, new_path);
         shift_idx += 1;
         idx0 += 1;
         idx1 += 1;
@@ -469,6 +1331,7 @@ fn diff_json_arr_lcs(
         diffs.push(DiffElem {
             diff: DiffChange::Remove(arr0[idx0].clone()),
             path: new_path,
+            location: None,
         });
         idx0 += 1;
     }
@@ -479,6 +1342,7 @@ fn diff_json_arr_lcs(
         diffs.push(DiffElem {
             diff: DiffChange::Add(arr1[idx1].clone()),
             path: new_path,
+            location: None,
         });
         idx1 += 1;
         shift_idx += 1;
@@ -489,13 +1353,27 @@ fn diff_json_arr_lcs(
 
 #[cfg(test)]
 mod tests {
+    use crate::comparator::Comparison;
     use crate::diff_json;
+    use crate::diff_json_simple;
+    use crate::diff_json_str;
+    use crate::diff_json_val;
+    use crate::normalizer;
     use crate::read_json_str;
+    use crate::span::SourceMap;
+    use crate::tree_edit::TreeEditCosts;
+    use crate::ArrDiffMethod;
     use crate::DiffChange;
     use crate::DiffElem;
+    use crate::DiffEngine;
+    use crate::DiffOptions;
+    use crate::JsonDiff;
     use crate::Path;
     use crate::PathElem;
+    use crate::PathMode;
     use crate::Value;
+    use jsonpatch::JsonPatch;
+    use jsonptr::pointer::JsonPointer;
     use serde_json::Number;
 
     fn check_diff(original: &str, dest: &str, mut expect_diff: Vec<DiffElem>) {
@@ -532,6 +1410,7 @@ mod tests {
                 new_val: Value::Bool(false),
             },
             path: Path::new(vec![PathElem::Key("x".to_string())]),
+            location: None,
         };
 
         check_diff(s0, s1, vec![diff])
@@ -556,6 +1435,7 @@ mod tests {
             vec![DiffElem {
                 diff: DiffChange::Add(Value::Bool(false)),
                 path: Path::new(vec![PathElem::Key("new".to_string())]),
+                location: None,
             }],
         );
 
@@ -566,10 +1446,12 @@ mod tests {
                 DiffElem {
                     diff: DiffChange::Add(Value::Bool(false)),
                     path: Path::new(vec![PathElem::Key("new1".to_string())]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Add(Value::Null),
                     path: Path::new(vec![PathElem::Key("new2".to_string())]),
+                    location: None,
                 },
             ],
         );
@@ -584,10 +1466,12 @@ mod tests {
                         PathElem::Key("b".to_string()),
                         PathElem::Key("b".to_string()),
                     ]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Add(Value::Null),
                     path: Path::new(vec![PathElem::Key("c".to_string())]),
+                    location: None,
                 },
             ],
         );
@@ -606,6 +1490,7 @@ mod tests {
             vec![DiffElem {
                 diff: DiffChange::Remove(Value::Bool(false)),
                 path: Path::new(vec![PathElem::Key("old".to_string())]),
+                location: None,
             }],
         );
 
@@ -616,10 +1501,12 @@ mod tests {
                 DiffElem {
                     diff: DiffChange::Remove(Value::Bool(false)),
                     path: Path::new(vec![PathElem::Key("old1".to_string())]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Remove(Value::Null),
                     path: Path::new(vec![PathElem::Key("old2".to_string())]),
+                    location: None,
                 },
             ],
         );
@@ -634,15 +1521,34 @@ mod tests {
                         PathElem::Key("b".to_string()),
                         PathElem::Key("b".to_string()),
                     ]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Remove(Value::Null),
                     path: Path::new(vec![PathElem::Key("c".to_string())]),
+                    location: None,
                 },
             ],
         );
     }
 
+    #[test]
+    fn test_arr_diff_simple() {
+        let json0 = r#"{"a": [1, 2], "b": [3]}"#;
+        let json1 = r#"{"a": [1, 2, 4], "b": [3]}"#;
+        assert_eq!(
+            diff_json_simple(json0, json1).unwrap(),
+            vec![DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: read_json_str("[1, 2]").unwrap(),
+                    new_val: read_json_str("[1, 2, 4]").unwrap(),
+                },
+                path: Path::new(vec![PathElem::Key("a".to_owned())]),
+                location: None,
+            }]
+        );
+    }
+
     #[test]
     fn test_arr_diff() {
         let json1 = r#"{"a": [1, 2, 3, 6, 7, 8, 9, 10]}"#;
@@ -654,14 +1560,17 @@ mod tests {
                 DiffElem {
                     diff: DiffChange::Add(Value::Number(Number::from(0))),
                     path: Path::new(vec![PathElem::Key("a".to_owned()), PathElem::Index(0)]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Remove(Value::Number(Number::from(2))),
                     path: Path::new(vec![PathElem::Key("a".to_owned()), PathElem::Index(2)]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Remove(Value::Number(Number::from(6))),
                     path: Path::new(vec![PathElem::Key("a".to_owned()), PathElem::Index(3)]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Replace {
@@ -669,12 +1578,13 @@ mod tests {
                         new_val: Value::Number(Number::from(13)),
                     },
                     path: Path::new(vec![PathElem::Key("a".to_owned()), PathElem::Index(6)]),
+                    location: None,
                 },
             ],
         );
     }
 
-    // consecutive deletions
+    // consecutive deletions, each at the index left by the previous one
     #[test]
     fn test_arr_diff2() {
         let json0 = "[0,1,2,3]";
@@ -686,10 +1596,12 @@ mod tests {
                 DiffElem {
                     diff: DiffChange::Remove(Value::Number(Number::from(1))),
                     path: Path::new(vec![PathElem::Index(1)]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Remove(Value::Number(Number::from(2))),
-                    path: Path::new(vec![PathElem::Index(2)]),
+                    path: Path::new(vec![PathElem::Index(1)]),
+                    location: None,
                 },
             ],
         );
@@ -706,14 +1618,17 @@ mod tests {
                 DiffElem {
                     diff: DiffChange::Remove(Value::Number(Number::from(1))),
                     path: Path::new(vec![PathElem::Index(1)]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Remove(Value::Number(Number::from(2))),
-                    path: Path::new(vec![PathElem::Index(2)]),
+                    path: Path::new(vec![PathElem::Index(1)]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Add(Value::Number(Number::from(4))),
                     path: Path::new(vec![PathElem::Index(2)]),
+                    location: None,
                 },
             ],
         );
@@ -757,6 +1672,7 @@ mod tests {
                         PathElem::Index(1),
                         PathElem::Key("e".to_owned()),
                     ]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Replace {
@@ -768,6 +1684,7 @@ mod tests {
                         PathElem::Index(1),
                         PathElem::Key("c".to_owned()),
                     ]),
+                    location: None,
                 },
             ],
         );
@@ -806,6 +1723,7 @@ mod tests {
                         PathElem::Index(1),
                         PathElem::Key("e".to_owned()),
                     ]),
+                    location: None,
                 },
                 DiffElem {
                     diff: DiffChange::Replace {
@@ -817,8 +1735,934 @@ mod tests {
                         PathElem::Index(1),
                         PathElem::Key("c".to_owned()),
                     ]),
+                    location: None,
                 },
             ],
         );
     }
+
+    #[test]
+    fn test_diff_in_document_order() {
+        let json0 = r#"{"a": 1, "b": 1, "c": 1, "d": [1, 2], "x": {"y": 1, "z": 1}}"#;
+        let json1 = r#"{"c": 2, "d": [0, 1], "e": 1, "f": 1, "x": {"w": 1, "y": 2}}"#;
+        let key = |s: &str| PathElem::Key(s.to_owned());
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Remove(Value::Number(Number::from(1))),
+                path: Path::new(vec![key("a")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Remove(Value::Number(Number::from(1))),
+                path: Path::new(vec![key("b")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::Number(Number::from(1)),
+                    new_val: Value::Number(Number::from(2)),
+                },
+                path: Path::new(vec![key("c")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Add(Value::Number(Number::from(0))),
+                path: Path::new(vec![key("d"), PathElem::Index(0)]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Remove(Value::Number(Number::from(2))),
+                path: Path::new(vec![key("d"), PathElem::Index(2)]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::Number(Number::from(1)),
+                    new_val: Value::Number(Number::from(2)),
+                },
+                path: Path::new(vec![key("x"), key("y")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Remove(Value::Number(Number::from(1))),
+                path: Path::new(vec![key("x"), key("z")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Add(Value::Number(Number::from(1))),
+                path: Path::new(vec![key("x"), key("w")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Add(Value::Number(Number::from(1))),
+                path: Path::new(vec![key("e")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Add(Value::Number(Number::from(1))),
+                path: Path::new(vec![key("f")]),
+                location: None,
+            },
+        ];
+        // no sorting here, the order itself is part of the result
+        assert_eq!(diff_json(json0, json1).unwrap(), expect_diff);
+        assert_eq!(
+            JsonDiff::diff_json(json0, json1).unwrap().diffs(),
+            expect_diff.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_diff_output_is_stable() {
+        let json0 = r#"{"k1": 1, "k2": 2, "k3": 3, "k4": 4, "k5": 5, "k6": 6}"#;
+        let json1 = r#"{"n1": 1, "n2": 2, "n3": 3, "n4": 4, "n5": 5, "k6": 7}"#;
+        let render = || {
+            let json_diff = JsonDiff::diff_json(json0, json1).unwrap();
+            let elems: Vec<String> = json_diff.diffs().iter().map(|d| d.to_string()).collect();
+            let add_keys = json_diff.get_add_keys(&Path::empty()).unwrap().clone();
+            (elems, add_keys)
+        };
+        let first = render();
+        assert_eq!(first.1, vec!["n1", "n2", "n3", "n4", "n5"]);
+        for _ in 0..20 {
+            assert_eq!(render(), first);
+        }
+    }
+
+    #[test]
+    fn test_added_keys_anchor() {
+        let json0 = r#"{"a": 1, "c": 1, "x": 1}"#;
+        let json1 = r#"{"a": 1, "b": 1, "c": 1, "d": 1, "e": 1}"#;
+        let json_diff = JsonDiff::diff_json(json0, json1).unwrap();
+        let root = Path::empty();
+        assert!(json_diff.get_add_keys_after(&root, None).is_empty());
+        assert_eq!(json_diff.get_add_keys_after(&root, Some("a")), vec!["b"]);
+        assert_eq!(
+            json_diff.get_add_keys_after(&root, Some("c")),
+            vec!["d", "e"]
+        );
+        assert!(json_diff.get_add_keys_after(&root, Some("x")).is_empty());
+
+        let json0 = r#"{"m": 1}"#;
+        let json1 = r#"{"a": 1, "m": 1, "z": 1}"#;
+        let json_diff = JsonDiff::diff_json(json0, json1).unwrap();
+        assert_eq!(json_diff.get_add_keys_after(&root, None), vec!["a"]);
+        assert_eq!(json_diff.get_add_keys_after(&root, Some("m")), vec!["z"]);
+    }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn test_diff_keep_key_order() {
+        let json0 = r#"{"z": 1, "y": 1, "x": {"b": 1, "a": 1}}"#;
+        let json1 = r#"{"x": {"b": 2, "c": 1, "a": 2}, "y": 2, "w": 1}"#;
+        let paths: Vec<String> = diff_json(json0, json1)
+            .unwrap()
+            .iter()
+            .map(|d| d.path_str())
+            .collect();
+        assert_eq!(paths, vec!["/z", "/y", "/x/b", "/x/a", "/x/c", "/w"]);
+
+        let json_diff = JsonDiff::diff_json(json0, json1).unwrap();
+        let x_path = Path::new(vec![PathElem::Key("x".to_owned())]);
+        assert_eq!(json_diff.get_add_keys_after(&x_path, Some("b")), vec!["c"]);
+    }
+
+    #[test]
+    fn test_key_order() {
+        let json0 = r#"{"a": 1, "b": {"x": 1, "y": 2}, "c": 3}"#;
+        let json1 = r#"{"c": 3, "a": 1, "b": {"y": 2, "x": 1}, "d": 4}"#;
+        let opts = DiffOptions {
+            key_order: true,
+            ..Default::default()
+        };
+        let add_d = DiffElem {
+            diff: DiffChange::Add(Value::Number(Number::from(4))),
+            path: Path::new(vec![PathElem::Key("d".to_owned())]),
+            location: None,
+        };
+        // not reported by default
+        assert_eq!(diff_json(json0, json1).unwrap(), vec![add_d.clone()]);
+
+        let diffs = diff_json_str(json0, json1, &opts).unwrap();
+        if cfg!(feature = "preserve_order") {
+            let to_vec = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect();
+            let expect_diff = vec![
+                DiffElem {
+                    diff: DiffChange::KeyOrder {
+                        old: to_vec(&["a", "b", "c"]),
+                        new: to_vec(&["c", "a", "b"]),
+                    },
+                    path: Path::empty(),
+                    location: None,
+                },
+                DiffElem {
+                    diff: DiffChange::KeyOrder {
+                        old: to_vec(&["x", "y"]),
+                        new: to_vec(&["y", "x"]),
+                    },
+                    path: Path::new(vec![PathElem::Key("b".to_owned())]),
+                    location: None,
+                },
+                add_d,
+            ];
+            assert_eq!(diffs, expect_diff);
+
+            let json_diff = JsonDiff::diff_with_options(
+                &read_json_str(json0).unwrap(),
+                &read_json_str(json1).unwrap(),
+                &opts,
+            );
+            assert!(json_diff.get_diffchange(&Path::empty()).is_none());
+            assert_eq!(
+                json_diff.get_key_order_change(&Path::empty()),
+                Some(&expect_diff[0].diff)
+            );
+        } else {
+            // keys are always sorted
+            assert_eq!(diffs, vec![add_d]);
+        }
+    }
+
+    #[test]
+    fn test_null_as_missing() {
+        let json0 = r#"{"a": null, "b": {"c": 1}, "d": 1}"#;
+        let json1 = r#"{"b": {"c": 1, "e": null}, "d": null}"#;
+        let opts = DiffOptions {
+            null_as_missing: true,
+            ..Default::default()
+        };
+        // a key set to null is still not the same as a null value
+        let expect_diff = vec![DiffElem {
+            diff: DiffChange::Replace {
+                old_val: Value::Number(Number::from(1)),
+                new_val: Value::Null,
+            },
+            path: Path::new(vec![PathElem::Key("d".to_owned())]),
+            location: None,
+        }];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+        assert_eq!(diff_json(json0, json1).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_missing_as_default() {
+        let json0 = r#"{"name": "x", "retries": 3, "tls": {"verify": true}}"#;
+        let json1 = r#"{"name": "x", "tls": {}, "timeout": 30, "port": 8080}"#;
+        let opts = DiffOptions {
+            defaults: Some(
+                read_json_str(
+                    r#"{"retries": 3, "timeout": 30, "port": 80, "tls": {"verify": true}}"#,
+                )
+                .unwrap(),
+            ),
+            ..Default::default()
+        };
+        let expect_diff = vec![DiffElem {
+            diff: DiffChange::Add(Value::Number(Number::from(8080))),
+            path: Path::new(vec![PathElem::Key("port".to_owned())]),
+            location: None,
+        }];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+    }
+
+    #[test]
+    fn test_normalizers() {
+        let json0 =
+            r#"{"name": " Alice ", "tags": ["b", "a"], "at": "2022-08-01T10:00:00+02:00", "n": 1}"#;
+        let json1 =
+            r#"{"name": "alice", "tags": ["a", "b"], "at": "2022-08-01T08:00:00Z", "n": 2}"#;
+        let opts = DiffOptions {
+            normalizers: vec![
+                normalizer::from_spec("trim").unwrap(),
+                normalizer::from_spec("casefold").unwrap(),
+                normalizer::from_spec("timestamp").unwrap(),
+                normalizer::from_spec("sort-arrays=/tags").unwrap(),
+            ],
+            ..Default::default()
+        };
+        let expect_diff = vec![DiffElem {
+            diff: DiffChange::Replace {
+                old_val: Value::Number(Number::from(1)),
+                new_val: Value::Number(Number::from(2)),
+            },
+            path: Path::new(vec![PathElem::Key("n".to_owned())]),
+            location: None,
+        }];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+    }
+
+    #[test]
+    fn test_lowercase_keys() {
+        let json0: Value = serde_json::from_str(r#"{"Age": 1, "Name": "a", "Tags": [1]}"#).unwrap();
+        let json1: Value =
+            serde_json::from_str(r#"{"age": 1, "city": "x", "name": "b", "tags": [1]}"#).unwrap();
+        let opts = DiffOptions {
+            normalizers: vec![normalizer::from_spec("lowercase-keys").unwrap()],
+            ..Default::default()
+        };
+        let name = Path::new(vec![PathElem::Key("name".to_owned())]);
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Add(Value::String("x".to_owned())),
+                path: Path::new(vec![PathElem::Key("city".to_owned())]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Rename {
+                    from_key: "Name".to_owned(),
+                },
+                path: name.clone(),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::String("a".to_owned()),
+                    new_val: Value::String("b".to_owned()),
+                },
+                path: name.clone(),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_val(&json0, &json1, &opts), expect_diff);
+
+        // the added key follows the key of the left document
+        let json_diff = JsonDiff::diff_with_options(&json0, &json1, &opts);
+        assert_eq!(
+            json_diff.get_add_keys_after(&Path::empty(), Some("Age")),
+            vec!["city"]
+        );
+        // the rename and the change of the value are both kept
+        assert_eq!(json_diff.get_rename_source(&name), Some(&"Name".to_owned()));
+        assert!(matches!(
+            json_diff.get_diffchange(&name),
+            Some(DiffChange::Replace { .. })
+        ));
+        assert_eq!(
+            json_diff.left_path(&name),
+            Path::new(vec![PathElem::Key("Name".to_owned())])
+        );
+    }
+
+    #[test]
+    fn test_normalizers_report_original_values() {
+        let json0 = r#"[" a", "b", " c"]"#;
+        let json1 = r#"["x", "a", "b", "C "]"#;
+        let opts = DiffOptions {
+            normalizers: vec![normalizer::from_spec("trim").unwrap()],
+            ..Default::default()
+        };
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Add(Value::String("x".to_owned())),
+                path: Path::new(vec![PathElem::Index(0)]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::String(" c".to_owned()),
+                    new_val: Value::String("C ".to_owned()),
+                },
+                path: Path::new(vec![PathElem::Index(3)]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+    }
+
+    #[test]
+    fn test_comparators() {
+        // urls are equal regardless of the order of the query params
+        let url_comparator = |_: &Path, v0: &Value, v1: &Value| match (v0.as_str(), v1.as_str()) {
+            (Some(s0), Some(s1)) if s0.contains('?') && s1.contains('?') => {
+                let split = |s: &str| {
+                    let (base, query) = s.split_once('?').unwrap();
+                    let mut params: Vec<String> = query.split('&').map(|p| p.to_owned()).collect();
+                    params.sort();
+                    (base.to_owned(), params)
+                };
+                if split(s0) == split(s1) {
+                    Comparison::Equal
+                } else {
+                    Comparison::Different
+                }
+            }
+            _ => Comparison::Recurse,
+        };
+        // versions are opaque, never look into them
+        let version_comparator = |path: &Path, v0: &Value, v1: &Value| {
+            if path.current_key().as_deref() == Some("version") && v0 != v1 {
+                Comparison::Different
+            } else {
+                Comparison::Recurse
+            }
+        };
+        let opts = DiffOptions {
+            comparators: vec![Box::new(url_comparator), Box::new(version_comparator)],
+            ..Default::default()
+        };
+        let json0 = r#"{"links": ["http://a?x=1&y=2", "http://b?q=1"], "version": {"major": 1, "minor": 2}}"#;
+        let json1 = r#"{"links": ["http://c?z=1", "http://a?y=2&x=1", "http://b?q=1"], "version": {"major": 1, "minor": 3}}"#;
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Add(Value::String("http://c?z=1".to_owned())),
+                path: Path::new(vec![PathElem::Key("links".to_owned()), PathElem::Index(0)]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: read_json_str(r#"{"major": 1, "minor": 2}"#).unwrap(),
+                    new_val: read_json_str(r#"{"major": 1, "minor": 3}"#).unwrap(),
+                },
+                path: Path::new(vec![PathElem::Key("version".to_owned())]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+    }
+
+    #[test]
+    fn test_same_value() {
+        // urls are equal regardless of the order of the query params
+        let url_comparator = |_: &Path, v0: &Value, v1: &Value| match (v0.as_str(), v1.as_str()) {
+            (Some(s0), Some(s1)) if s0.starts_with("http") && s1.starts_with("http") => {
+                if s0.split('?').next() == s1.split('?').next() {
+                    Comparison::Equal
+                } else {
+                    Comparison::Different
+                }
+            }
+            _ => Comparison::Recurse,
+        };
+        let opts = DiffOptions {
+            comparators: vec![Box::new(url_comparator)],
+            normalizers: vec![normalizer::from_spec("lowercase-keys").unwrap()],
+            null_as_missing: true,
+            ..Default::default()
+        };
+        // the same answer as an empty diff, without diffing the values
+        for (json0, json1) in [
+            (
+                r#"{"a": [1, {"b": "http://x?1"}]}"#,
+                r#"{"a": [1, {"b": "http://x?2"}]}"#,
+            ),
+            (
+                r#"{"a": [1, {"b": "http://x"}]}"#,
+                r#"{"a": [1, {"b": "http://y"}]}"#,
+            ),
+            (r#"{"A": 1, "n": null}"#, r#"{"a": 1}"#),
+            (r#"{"A": 1}"#, r#"{"a": 2}"#),
+            (r#"{"a": 1}"#, r#"{"a": 1, "b": 2}"#),
+            (r#"[1, 2]"#, r#"[1, 2, 3]"#),
+            (r#"[1, 2]"#, r#"[2, 1]"#),
+            (r#""1""#, r#"1"#),
+        ] {
+            let val0 = read_json_str(json0).unwrap();
+            let val1 = read_json_str(json1).unwrap();
+            assert_eq!(
+                opts.same_value(&Path::empty(), &val0, &val1),
+                diff_json_val(&val0, &val1, &opts).is_empty(),
+                "{} {}",
+                json0,
+                json1
+            );
+        }
+    }
+
+    fn check_patch(json0: &str, json1: &str, opts: &DiffOptions) {
+        let val0 = read_json_str(json0).unwrap();
+        let val1 = read_json_str(json1).unwrap();
+        let patch = JsonDiff::diff_with_options(&val0, &val1, opts).to_patch();
+        assert_eq!(patch.apply(&val0).unwrap(), val1, "{} -> {}", json0, json1);
+        // the patch also applies after a round trip through its json form
+        let patch_str = serde_json::to_string(&patch).unwrap();
+        let parsed = JsonPatch::try_from(patch_str.as_str()).unwrap();
+        assert_eq!(parsed.apply(&val0).unwrap(), val1, "{}", patch_str);
+    }
+
+    #[test]
+    fn test_patch_from_diff() {
+        let pairs = [
+            (r#"{"a": 1}"#, r#"{"a": 1, "b": {"c": [1, 2]}}"#),
+            (r#"{"a": 1, "b": 2}"#, r#"{"b": 3}"#),
+            ("[0, 1, 2, 3]", "[0, 2]"),
+            ("[0, 1, 2, 3]", "[0, 3, 4]"),
+            ("[0, 1, 2, 3, 4]", "[0, 3]"),
+            ("[1, 2, 3, 6, 7, 8, 9, 10]", "[0, 1, 3, 7, 8, 9, 13]"),
+            (r#"[1, {"a": 1}]"#, r#"[0, 1, {"a": 2, "b": [1]}]"#),
+            (r#"{"a/b": {"~c": 1}}"#, r#"{"a/b": {"~c": 2}}"#),
+            ("true", r#"{"a": null}"#),
+            (r#"{"a": 1, "b": [1]}"#, r#"{"a": null, "b": [null, 1]}"#),
+            ("1", "null"),
+        ];
+        for (json0, json1) in pairs {
+            check_patch(json0, json1, &DiffOptions::default());
+            check_patch(json1, json0, &DiffOptions::default());
+        }
+    }
+
+    #[test]
+    fn test_rename() {
+        let json0 = r#"{"userName": "bob", "age": 1}"#;
+        let json1 = r#"{"username": "bob", "age": 1}"#;
+        let opts = DiffOptions {
+            rename_threshold: Some(1.0),
+            ..Default::default()
+        };
+        let expect_diff = vec![DiffElem {
+            diff: DiffChange::Rename {
+                from_key: "userName".to_owned(),
+            },
+            path: Path::new(vec![PathElem::Key("username".to_owned())]),
+            location: None,
+        }];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+        check_patch(json0, json1, &opts);
+        // off by default
+        assert_eq!(diff_json(json0, json1).unwrap().len(), 2);
+
+        // near-identical values with a lower threshold
+        let json0 = r#"{"user": {"name": "a", "id": 1, "x": 2}, "other": {"y": 1}}"#;
+        let json1 = r#"{"account": {"name": "a", "id": 1, "x": 3}, "new": {"z": 1}}"#;
+        let opts = DiffOptions {
+            rename_threshold: Some(0.5),
+            ..Default::default()
+        };
+        let key = |s: &str| PathElem::Key(s.to_owned());
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Remove(read_json_str(r#"{"y": 1}"#).unwrap()),
+                path: Path::new(vec![key("other")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Rename {
+                    from_key: "user".to_owned(),
+                },
+                path: Path::new(vec![key("account")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::Number(Number::from(2)),
+                    new_val: Value::Number(Number::from(3)),
+                },
+                path: Path::new(vec![key("account"), key("x")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Add(read_json_str(r#"{"z": 1}"#).unwrap()),
+                path: Path::new(vec![key("new")]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+        check_patch(json0, json1, &opts);
+
+        let json_diff = JsonDiff::diff_with_options(
+            &read_json_str(json0).unwrap(),
+            &read_json_str(json1).unwrap(),
+            &opts,
+        );
+        assert_eq!(
+            json_diff.get_rename_target(&Path::new(vec![key("user")])),
+            Some(&"account".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_moves_and_copies() {
+        let json0 = r#"{"a": {"x": {"host": "h", "port": 1}, "k": 1}, "b": {"z": 1}, "c": [1, 2]}"#;
+        let json1 = r#"{"a": {"k": 1}, "b": {"z": 1, "y": {"host": "h", "port": 1}}, "c": [1, 2], "d": [1, 2]}"#;
+        let opts = DiffOptions {
+            detect_moves: true,
+            detect_copies: true,
+            ..Default::default()
+        };
+        let key = |s: &str| PathElem::Key(s.to_owned());
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Move {
+                    from: Path::new(vec![key("a"), key("x")]),
+                },
+                path: Path::new(vec![key("b"), key("y")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Copy {
+                    from: Path::new(vec![key("c")]),
+                },
+                path: Path::new(vec![key("d")]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+        // off by default
+        assert_eq!(diff_json(json0, json1).unwrap().len(), 3);
+
+        let json_diff = JsonDiff::diff_with_options(
+            &read_json_str(json0).unwrap(),
+            &read_json_str(json1).unwrap(),
+            &opts,
+        );
+        assert_eq!(
+            json_diff.get_move_target(&Path::new(vec![key("a"), key("x")])),
+            Some(&Path::new(vec![key("b"), key("y")]))
+        );
+
+        // equal objects whose keys are in another order
+        let json0 = r#"{"a": {"host": "h", "port": 1}, "c": {"p": 1, "q": 2}}"#;
+        let json1 =
+            r#"{"b": {"port": 1, "host": "h"}, "c": {"p": 1, "q": 2}, "d": {"q": 2, "p": 1}}"#;
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Move {
+                    from: Path::new(vec![key("a")]),
+                },
+                path: Path::new(vec![key("b")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Copy {
+                    from: Path::new(vec![key("c")]),
+                },
+                path: Path::new(vec![key("d")]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+
+        let pairs = [
+            (json0, json1),
+            // the addition comes before the removal
+            (r#"{"z": {"v": [1]}}"#, r#"{"a": {"v": [1]}}"#),
+            // into and out of a renamed key
+            (
+                r#"{"old": {"n": 1, "m": {"v": 1}}, "p": {"q": {"w": 2}}}"#,
+                r#"{"new": {"n": 1, "q": {"w": 2}}, "p": {"m": {"v": 1}}}"#,
+            ),
+            // arrays are not considered
+            (r#"[{"a": 1}, {"b": 1}]"#, r#"[{"b": 1}, {"a": 1}]"#),
+            // the source of a copy is changed
+            (r#"{"a": {"b": 1}}"#, r#"{"a": {"b": 2}, "c": {"b": 1}}"#),
+            // the source of a copy is below a reordered object
+            (
+                r#"{"a": {"b": {"c": 1}, "d": 1}}"#,
+                r#"{"a": {"d": 1, "b": {"c": 1}}, "e": {"c": 1}}"#,
+            ),
+        ];
+        let opts = DiffOptions {
+            detect_moves: true,
+            detect_copies: true,
+            rename_threshold: Some(0.3),
+            key_order: true,
+            ..Default::default()
+        };
+        for (json0, json1) in pairs {
+            check_patch(json0, json1, &opts);
+            check_patch(json1, json0, &opts);
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_arr() {
+        let json0 = r#"[{"id": 1, "name": "a", "tag": "x"}, {"id": 2, "name": "b", "tag": "y"}]"#;
+        let json1 = r#"[{"id": 0, "name": "z", "tag": "z"}, {"id": 1, "name": "a", "tag": "xx"}, {"id": 2, "name": "b", "tag": "y"}]"#;
+        let opts = DiffOptions {
+            arr_diff: ArrDiffMethod::Fuzzy { threshold: 0.5 },
+            ..Default::default()
+        };
+        let key = |s: &str| PathElem::Key(s.to_owned());
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Add(
+                    read_json_str(r#"{"id": 0, "name": "z", "tag": "z"}"#).unwrap(),
+                ),
+                path: Path::new(vec![PathElem::Index(0)]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::String("x".to_owned()),
+                    new_val: Value::String("xx".to_owned()),
+                },
+                path: Path::new(vec![PathElem::Index(1), key("tag")]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+        // the plain lcs pairs the changed record with the added one
+        assert_eq!(diff_json(json0, json1).unwrap().len(), 4);
+
+        // dissimilar elements are removed and added instead of replaced
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Remove(Value::from(1)),
+                path: Path::new(vec![PathElem::Index(0)]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Add(Value::from(2)),
+                path: Path::new(vec![PathElem::Index(0)]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str("[1]", "[2]", &opts).unwrap(), expect_diff);
+
+        let pairs = [
+            (json0, json1),
+            (
+                r#"[{"a": 1, "b": 2}, {"c": 3, "d": 4}, 5]"#,
+                r#"[6, {"c": 3, "d": 5}, {"a": 1, "b": 2, "e": 7}]"#,
+            ),
+            ("[1, 2, 3, 4]", "[4, 3, 2, 1]"),
+            (r#"[[1, 2], [3, 4]]"#, r#"[[3, 4, 5], [1]]"#),
+        ];
+        for (json0, json1) in pairs {
+            check_patch(json0, json1, &opts);
+            check_patch(json1, json0, &opts);
+        }
+
+        // too long a stretch of changed elements is not aligned
+        let records = |n: usize, v: i32| -> Value {
+            (0..n)
+                .map(|id| serde_json::json!({"id": id, "v": v}))
+                .collect()
+        };
+        let few = diff_json_val(&records(3, 0), &records(3, 1), &opts);
+        assert!(few
+            .iter()
+            .all(|d| matches!(d.diff, DiffChange::Replace { .. })));
+        let many = diff_json_val(&records(300, 0), &records(300, 1), &opts);
+        assert_eq!(many.len(), 600);
+        assert!(many.iter().all(|d| d.path.len() == 1));
+    }
+
+    #[test]
+    fn test_tree_edit_engine() {
+        let opts = DiffOptions {
+            engine: DiffEngine::TreeEdit(TreeEditCosts::default()),
+            ..Default::default()
+        };
+        let key = |s: &str| PathElem::Key(s.to_owned());
+        // the renamed key keeps its value, the changed value stays in place
+        let json0 = r#"{"a": {"x": 1, "y": [1, 2, 3]}, "b": true}"#;
+        let json1 = r#"{"c": {"x": 1, "y": [1, 3]}, "b": false}"#;
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::Bool(true),
+                    new_val: Value::Bool(false),
+                },
+                path: Path::new(vec![key("b")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Rename {
+                    from_key: "a".to_owned(),
+                },
+                path: Path::new(vec![key("c")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Remove(Value::from(2)),
+                path: Path::new(vec![key("c"), key("y"), PathElem::Index(1)]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+
+        let pairs = [
+            (json0, json1),
+            (r#"{"a": 1}"#, r#"{"a": 1, "b": {"c": [1, 2]}}"#),
+            ("[0, 1, 2, 3]", "[0, 3, 4]"),
+            ("[1, 2, 3, 6, 7, 8, 9, 10]", "[0, 1, 3, 7, 8, 9, 13]"),
+            (r#"[1, {"a": 1}]"#, r#"[0, 1, {"a": 2, "b": [1]}]"#),
+            ("[[1, 2], 3]", "[1, 2, [3]]"),
+            (r#"{"a": {"b": {"c": 1}}}"#, r#"{"a": {"c": 1}, "b": {}}"#),
+            (r#"{"a": [1], "b": 2}"#, r#"{"b": [1], "a": 2}"#),
+            ("true", r#"{"a": null}"#),
+        ];
+        for (json0, json1) in pairs {
+            check_patch(json0, json1, &opts);
+            check_patch(json1, json0, &opts);
+        }
+    }
+
+    #[test]
+    fn test_diff_at() {
+        let val0 =
+            read_json_str(r#"{"spec": {"items": [{"a": 1}, {"b": 2}]}, "status": 1}"#).unwrap();
+        let val1 =
+            read_json_str(r#"{"spec": {"items": [{"a": 1}, {"b": 3}]}, "status": 2}"#).unwrap();
+        let json_ptr = JsonPointer::new("/spec/items/1").unwrap();
+        let key = |s: &str| PathElem::Key(s.to_owned());
+        let replace = DiffChange::Replace {
+            old_val: Value::Number(Number::from(2)),
+            new_val: Value::Number(Number::from(3)),
+        };
+
+        let opts = DiffOptions::default();
+        let json_diff =
+            JsonDiff::diff_at(&val0, &val1, &json_ptr, &opts, PathMode::Relative).unwrap();
+        let expect_diff = vec![DiffElem {
+            diff: replace.clone(),
+            path: Path::new(vec![key("b")]),
+            location: None,
+        }];
+        assert_eq!(json_diff.diffs(), expect_diff);
+
+        let json_diff =
+            JsonDiff::diff_at(&val0, &val1, &json_ptr, &opts, PathMode::Absolute).unwrap();
+        let expect_diff = vec![DiffElem {
+            diff: replace,
+            path: Path::new(vec![
+                key("spec"),
+                key("items"),
+                PathElem::Index(1),
+                key("b"),
+            ]),
+            location: None,
+        }];
+        assert_eq!(json_diff.diffs(), expect_diff);
+        // the change of /status is left out
+        let mut expect_val = val1.clone();
+        expect_val["status"] = Value::from(1);
+        assert_eq!(json_diff.to_patch().apply(&val0).unwrap(), expect_val);
+
+        let json_ptr = JsonPointer::new("/spec/other").unwrap();
+        assert!(JsonDiff::diff_at(&val0, &val1, &json_ptr, &opts, PathMode::Relative).is_err());
+    }
+
+    #[test]
+    fn test_locate() {
+        let src0 = "{\n  \"a\": [1, 2, 3, 4],\n  \"k\": \"v\",\n  \"old\": {\"x\": 1, \"y\": 1}\n}";
+        let src1 =
+            "{\n  \"a\": [0, 1, 3, 4, 5],\n  \"k\": \"w\",\n  \"new\": {\"x\": 2, \"y\": 1}\n}";
+        let opts = DiffOptions {
+            rename_threshold: Some(0.5),
+            ..Default::default()
+        };
+        let json_diff = JsonDiff::diff_sources(src0, src1, &opts).unwrap();
+        let located: Vec<(String, Option<&str>, Option<&str>)> = json_diff
+            .diffs()
+            .iter()
+            .map(|diff| {
+                let location = diff.location.unwrap();
+                (
+                    diff.path.to_string(),
+                    location.left.map(|span| &src0[span.start..span.end]),
+                    location.right.map(|span| &src1[span.start..span.end]),
+                )
+            })
+            .collect();
+        assert_eq!(
+            located,
+            vec![
+                ("/a/_0".to_owned(), None, Some("0")),
+                // the removal is at index 2 of the diff, after the added 0
+                ("/a/_2".to_owned(), Some("2"), None),
+                ("/a/_4".to_owned(), None, Some("5")),
+                ("/k".to_owned(), Some("\"v\""), Some("\"w\"")),
+                (
+                    "/new".to_owned(),
+                    Some("{\"x\": 1, \"y\": 1}"),
+                    Some("{\"x\": 2, \"y\": 1}")
+                ),
+                ("/new/x".to_owned(), Some("1"), Some("2")),
+            ]
+        );
+        let left_path =
+            json_diff.left_path(&Path::empty().clone_then_add_key("a").clone_then_add_idx(3));
+        assert_eq!(left_path.to_string(), "/a/_3");
+        let map0 = SourceMap::parse(src0).unwrap();
+        assert_eq!(map0.get(&left_path).map(|span| span.line), Some(2));
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let json0 = r#"{"s": 1, "spec": {"a": {"x": 1, "y": 2}, "b": [1, 2, 3]}, "t": [1]}"#;
+        let json1 = r#"{"s": 2, "spec": {"a": {"x": 2, "y": 3}, "b": [1, 3]}, "t": [1]}"#;
+        let opts = DiffOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let key = |s: &str| PathElem::Key(s.to_owned());
+        let val0 = read_json_str(json0).unwrap();
+        let val1 = read_json_str(json1).unwrap();
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: Value::from(1),
+                    new_val: Value::from(2),
+                },
+                path: Path::new(vec![key("s")]),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Collapsed {
+                    old_val: val0["spec"].clone(),
+                    new_val: val1["spec"].clone(),
+                    changes: 3,
+                },
+                path: Path::new(vec![key("spec")]),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+        check_patch(json0, json1, &opts);
+
+        // the root is collapsed at depth 0, equal values are not
+        let opts = DiffOptions {
+            max_depth: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap().len(), 1);
+        assert!(diff_json_str(json0, json0, &opts).unwrap().is_empty());
+        check_patch(json0, json1, &opts);
+    }
+
+    #[test]
+    fn test_max_depth_with_renames() {
+        let json0 = r#"{"owner": {"id": 7, "name": "bob", "role": "admin"}, "size": 1}"#;
+        let json1 = r#"{"size": 1, "user": {"id": 7, "name": "bob", "role": "editor"}}"#;
+        let opts = DiffOptions {
+            max_depth: Some(1),
+            rename_threshold: Some(0.5),
+            ..Default::default()
+        };
+        let user = Path::new(vec![PathElem::Key("user".to_owned())]);
+        let val0 = read_json_str(json0).unwrap();
+        let val1 = read_json_str(json1).unwrap();
+        let expect_diff = vec![
+            DiffElem {
+                diff: DiffChange::Rename {
+                    from_key: "owner".to_owned(),
+                },
+                path: user.clone(),
+                location: None,
+            },
+            DiffElem {
+                diff: DiffChange::Collapsed {
+                    old_val: val0["owner"].clone(),
+                    new_val: val1["user"].clone(),
+                    changes: 1,
+                },
+                path: user.clone(),
+                location: None,
+            },
+        ];
+        assert_eq!(diff_json_str(json0, json1, &opts).unwrap(), expect_diff);
+        check_patch(json0, json1, &opts);
+
+        // the collapsed change does not hide the rename
+        let json_diff = JsonDiff::diff_with_options(&val0, &val1, &opts);
+        assert_eq!(
+            json_diff.get_rename_source(&user),
+            Some(&"owner".to_owned())
+        );
+        assert!(matches!(
+            json_diff.get_diffchange(&user),
+            Some(DiffChange::Collapsed { changes: 1, .. })
+        ));
+    }
 }
@@ -0,0 +1,151 @@
+use crate::similarity::value_hash;
+use crate::{DiffChange, DiffElem, Path, PathElem};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Only whole objects and arrays are moved or copied, a scalar that shows up somewhere else
+/// is more likely a coincidence. Paths through arrays are left alone as their indices depend
+/// on the other changes of the array.
+fn is_candidate(path: &Path, val: &Value) -> bool {
+    let non_empty = match val {
+        Value::Array(arr) => !arr.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+        _ => false,
+    };
+    non_empty && !path.is_empty() && path.iter().all(|elem| matches!(elem, PathElem::Key(_)))
+}
+
+fn is_prefix(prefix: &Path, path: &Path) -> bool {
+    prefix.len() <= path.len() && prefix.iter().zip(path.iter()).all(|(a, b)| a == b)
+}
+
+/// Collect the objects and arrays of `val` that are reachable by keys only.
+fn collect_subtrees<'a>(val: &'a Value, path: &mut Path, subtrees: &mut Vec<(Path, &'a Value)>) {
+    if is_candidate(path, val) {
+        subtrees.push((path.clone(), val));
+    }
+    if let Value::Object(map) = val {
+        for (k, v) in map.iter() {
+            path.push_key(k);
+            collect_subtrees(v, path, subtrees);
+            path.pop();
+        }
+    }
+}
+
+/// Replace a removed and an added value that are equal by one `DiffChange::Move`.
+///
+/// The move takes the later position of the two, by then the source still exists and the
+/// parent of the target does.
+pub(crate) fn detect_moves(diffs: Vec<DiffElem>) -> Vec<DiffElem> {
+    let mut removed: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (idx, diff) in diffs.iter().enumerate() {
+        if let DiffChange::Remove(val) = &diff.diff {
+            if is_candidate(&diff.path, val) {
+                removed.entry(value_hash(val)).or_default().push(idx);
+            }
+        }
+    }
+    if removed.is_empty() {
+        return diffs;
+    }
+
+    // position of the move -> the move
+    let mut moves: HashMap<usize, DiffElem> = HashMap::new();
+    let mut dropped = vec![false; diffs.len()];
+    for (idx, diff) in diffs.iter().enumerate() {
+        if let DiffChange::Add(val) = &diff.diff {
+            if !is_candidate(&diff.path, val) {
+                continue;
+            }
+            let candidates = match removed.get_mut(&value_hash(val)) {
+                Some(candidates) => candidates,
+                None => continue,
+            };
+            let found = candidates
+                .iter()
+                .position(|remove_idx| diffs[*remove_idx].diff.as_remove() == Some(val));
+            if let Some(pos) = found {
+                let remove_idx = candidates.remove(pos);
+                dropped[remove_idx.min(idx)] = true;
+                let move_elem = DiffElem {
+                    diff: DiffChange::Move {
+                        from: diffs[remove_idx].path.clone(),
+                    },
+                    path: diff.path.clone(),
+                    location: None,
+                };
+                moves.insert(remove_idx.max(idx), move_elem);
+            }
+        }
+    }
+
+    diffs
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| !dropped[*idx])
+        .map(|(idx, diff)| moves.remove(&idx).unwrap_or(diff))
+        .collect()
+}
+
+/// Replace an added value by a `DiffChange::Copy` when an equal value is kept unchanged
+/// somewhere else in the document.
+pub(crate) fn detect_copies(diffs: Vec<DiffElem>, left: &Value, right: &Value) -> Vec<DiffElem> {
+    let mut subtrees = vec![];
+    collect_subtrees(left, &mut Path::empty(), &mut subtrees);
+    let mut sources: HashMap<u64, Vec<(Path, &Value)>> = HashMap::new();
+    for (path, val) in subtrees {
+        sources
+            .entry(value_hash(val))
+            .or_default()
+            .push((path, val));
+    }
+
+    // a source must not be touched by any change, or it may be gone when it is copied
+    let mut touched: Vec<(Path, bool)> = vec![];
+    for diff in diffs.iter() {
+        match &diff.diff {
+            DiffChange::KeyOrder { .. } => touched.push((diff.path.clone(), true)),
+            DiffChange::Rename { from_key } => {
+                let parent = diff.path.parent_path().unwrap();
+                touched.push((parent.clone_then_add_key(from_key), false));
+                touched.push((diff.path.clone(), false));
+            }
+            DiffChange::Move { from } => {
+                touched.push((from.clone(), false));
+                touched.push((diff.path.clone(), false));
+            }
+            _ => touched.push((diff.path.clone(), false)),
+        }
+    }
+    let is_untouched = |path: &Path| {
+        touched.iter().all(|(touched_path, order_only)| {
+            // reordering the keys of a parent does not change the value
+            let below = is_prefix(touched_path, path) && !(*order_only && touched_path != path);
+            !below && !is_prefix(path, touched_path)
+        })
+    };
+
+    diffs
+        .into_iter()
+        .map(|diff| {
+            let val = match &diff.diff {
+                DiffChange::Add(val) if is_candidate(&diff.path, val) => val,
+                _ => return diff,
+            };
+            let source = sources.get(&value_hash(val)).and_then(|candidates| {
+                candidates.iter().find(|(path, source_val)| {
+                    *source_val == val && path.locate(right) == Some(val) && is_untouched(path)
+                })
+            });
+            match source {
+                Some((from, _)) => DiffElem {
+                    diff: DiffChange::Copy { from: from.clone() },
+                    path: diff.path,
+                    location: None,
+                },
+                None => diff,
+            }
+        })
+        .collect()
+}
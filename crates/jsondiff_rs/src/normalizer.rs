@@ -0,0 +1,291 @@
+use crate::Path;
+use crate::PathElem;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use jsonptr::pointer::JsonPointer;
+use serde_json::Value;
+use std::borrow::Cow;
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalize a value before it is compared, equality is then judged on the normalized values
+/// while the diff still reports the original ones.
+///
+/// The values are normalized one at a time while the documents are walked. A normalizer that
+/// rewrites a whole object or array (e.g. sorts it) makes the diff compare the normalized
+/// values below it as a whole, so it is best kept to the paths that need it.
+pub trait Normalizer {
+    /// Return the normalized form of `val` at `path`, or `None` to keep it as it is.
+    fn normalize(&self, path: &Path, val: &Value) -> Option<Value>;
+
+    /// Return the normalized form of a `key` of the object at `path`, or `None` to keep it.
+    /// The keys of two objects are paired by their normalized forms.
+    fn normalize_key(&self, _path: &Path, _key: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Trim leading and trailing whitespace of strings.
+pub struct TrimStrings;
+
+impl Normalizer for TrimStrings {
+    fn normalize(&self, _path: &Path, val: &Value) -> Option<Value> {
+        let s = val.as_str()?;
+        Some(Value::String(s.trim().to_owned()))
+    }
+}
+
+/// Compare strings case insensitively.
+pub struct CaseFold;
+
+impl Normalizer for CaseFold {
+    fn normalize(&self, _path: &Path, val: &Value) -> Option<Value> {
+        let s = val.as_str()?;
+        Some(Value::String(s.to_lowercase()))
+    }
+}
+
+/// Unicode NFC normalization of strings.
+pub struct UnicodeNfc;
+
+impl Normalizer for UnicodeNfc {
+    fn normalize(&self, _path: &Path, val: &Value) -> Option<Value> {
+        let s = val.as_str()?;
+        Some(Value::String(s.nfc().collect()))
+    }
+}
+
+/// Parse ISO-8601 (RFC 3339) timestamps, so the same instant written in different time
+/// zones or precisions is equal. Strings that are not timestamps are kept.
+pub struct Timestamps;
+
+impl Normalizer for Timestamps {
+    fn normalize(&self, _path: &Path, val: &Value) -> Option<Value> {
+        let s = val.as_str()?;
+        let instant = DateTime::parse_from_rfc3339(s).ok()?.with_timezone(&Utc);
+        Some(Value::String(
+            instant.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        ))
+    }
+}
+
+/// Compare arrays regardless of the order of their elements.
+pub struct SortArrays;
+
+impl Normalizer for SortArrays {
+    fn normalize(&self, _path: &Path, val: &Value) -> Option<Value> {
+        let arr = val.as_array()?;
+        let mut sorted = arr.clone();
+        sorted.sort_by_cached_key(|v| v.to_string());
+        Some(Value::Array(sorted))
+    }
+}
+
+/// Compare object keys case insensitively. The values of two keys that only differ by case
+/// are compared, and when they differ the key is reported renamed along with the changes.
+pub struct LowercaseKeys;
+
+impl Normalizer for LowercaseKeys {
+    fn normalize(&self, _path: &Path, _val: &Value) -> Option<Value> {
+        None
+    }
+
+    fn normalize_key(&self, _path: &Path, key: &str) -> Option<String> {
+        Some(key.to_lowercase())
+    }
+}
+
+/// Apply a normalizer only at a json pointer and below it.
+pub struct AtPath {
+    json_ptr: JsonPointer,
+    normalizer: Box<dyn Normalizer>,
+}
+
+impl AtPath {
+    pub fn new(json_ptr: &str, normalizer: Box<dyn Normalizer>) -> Result<Self> {
+        Ok(Self {
+            json_ptr: JsonPointer::new(json_ptr)?,
+            normalizer,
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        path.len() >= self.json_ptr.len()
+            && self
+                .json_ptr
+                .iter()
+                .zip(path.iter())
+                .all(|(token, elem)| match elem {
+                    PathElem::Key(key) => key == token.as_key(),
+                    PathElem::Index(idx) => idx.to_string() == token.as_key(),
+                })
+    }
+}
+
+impl Normalizer for AtPath {
+    fn normalize(&self, path: &Path, val: &Value) -> Option<Value> {
+        if self.matches(path) {
+            self.normalizer.normalize(path, val)
+        } else {
+            None
+        }
+    }
+
+    fn normalize_key(&self, path: &Path, key: &str) -> Option<String> {
+        if self.matches(path) {
+            self.normalizer.normalize_key(path, key)
+        } else {
+            None
+        }
+    }
+}
+
+/// Build a built-in normalizer from `name` or `name=json_pointer`, the latter only applies
+/// it at the pointer and below.
+///
+/// Names: `trim`, `casefold`, `nfc`, `timestamp`, `sort-arrays`, `lowercase-keys`.
+pub fn from_spec(spec: &str) -> Result<Box<dyn Normalizer>> {
+    let (name, json_ptr) = match spec.split_once('=') {
+        Some((name, json_ptr)) => (name, Some(json_ptr)),
+        None => (spec, None),
+    };
+    let normalizer: Box<dyn Normalizer> = match name {
+        "trim" => Box::new(TrimStrings),
+        "casefold" => Box::new(CaseFold),
+        "nfc" => Box::new(UnicodeNfc),
+        "timestamp" => Box::new(Timestamps),
+        "sort-arrays" => Box::new(SortArrays),
+        "lowercase-keys" => Box::new(LowercaseKeys),
+        _ => return Err(anyhow!("Unknown normalizer '{}'", name)),
+    };
+    match json_ptr {
+        Some(json_ptr) => Ok(Box::new(AtPath::new(json_ptr, normalizer)?)),
+        None => Ok(normalizer),
+    }
+}
+
+/// Apply all normalizers to `val`, but not to the values below it.
+pub(crate) fn normalize<'a>(
+    normalizers: &[Box<dyn Normalizer>],
+    path: &Path,
+    val: &'a Value,
+) -> Cow<'a, Value> {
+    let mut normalized = Cow::Borrowed(val);
+    for normalizer in normalizers {
+        if let Some(v) = normalizer.normalize(path, &normalized) {
+            normalized = Cow::Owned(v);
+        }
+    }
+    normalized
+}
+
+/// Apply all normalizers to a `key` of the object at `path`.
+pub(crate) fn normalize_key<'a>(
+    normalizers: &[Box<dyn Normalizer>],
+    path: &Path,
+    key: &'a str,
+) -> Cow<'a, str> {
+    let mut normalized = Cow::Borrowed(key);
+    for normalizer in normalizers {
+        if let Some(k) = normalizer.normalize_key(path, &normalized) {
+            normalized = Cow::Owned(k);
+        }
+    }
+    normalized
+}
+
+/// Apply all normalizers to `val` and, recursively, to everything below it.
+pub(crate) fn normalize_deep(
+    normalizers: &[Box<dyn Normalizer>],
+    path: &Path,
+    val: &Value,
+) -> Value {
+    match normalize(normalizers, path, val).as_ref() {
+        Value::Array(arr) => Value::Array(
+            arr.iter()
+                .enumerate()
+                .map(|(idx, v)| normalize_deep(normalizers, &path.clone_then_add_idx(idx), v))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let normalized_v = normalize_deep(normalizers, &path.clone_then_add_key(k), v);
+                    (
+                        normalize_key(normalizers, path, k).into_owned(),
+                        normalized_v,
+                    )
+                })
+                .collect(),
+        ),
+        normalized => normalized.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn normalize(normalizer: &dyn Normalizer, val: Value) -> Option<Value> {
+        normalizer.normalize(&Path::empty(), &val)
+    }
+
+    #[test]
+    fn test_string_normalizers() {
+        assert_eq!(normalize(&TrimStrings, json!("  a b ")), Some(json!("a b")));
+        assert_eq!(normalize(&CaseFold, json!("AbC")), Some(json!("abc")));
+        assert_eq!(
+            normalize(&UnicodeNfc, json!("e\u{0301}")),
+            Some(json!("\u{00e9}"))
+        );
+        assert_eq!(normalize(&TrimStrings, json!(1)), None);
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let t0 = normalize(&Timestamps, json!("2022-08-01T10:00:00+02:00"));
+        let t1 = normalize(&Timestamps, json!("2022-08-01T08:00:00.000Z"));
+        assert!(t0.is_some());
+        assert_eq!(t0, t1);
+        assert_eq!(normalize(&Timestamps, json!("yesterday")), None);
+    }
+
+    #[test]
+    fn test_container_normalizers() {
+        assert_eq!(
+            normalize(&SortArrays, json!([3, "a", 1])),
+            Some(json!(["a", 1, 3]))
+        );
+        assert_eq!(
+            LowercaseKeys.normalize_key(&Path::empty(), "Name"),
+            Some("name".to_owned())
+        );
+        assert_eq!(
+            normalize_deep(
+                &[Box::new(LowercaseKeys)],
+                &Path::empty(),
+                &json!({"A": {"B": 1}})
+            ),
+            json!({"a": {"b": 1}})
+        );
+    }
+
+    #[test]
+    fn test_at_path() {
+        let normalizer = from_spec("trim=/a/0").unwrap();
+        let path = Path::new(vec![PathElem::Key("a".to_owned()), PathElem::Index(0)]);
+        assert_eq!(normalizer.normalize(&path, &json!(" x")), Some(json!("x")));
+        let path = Path::new(vec![PathElem::Key("a".to_owned()), PathElem::Index(1)]);
+        assert_eq!(normalizer.normalize(&path, &json!(" x")), None);
+        assert!(from_spec("unknown").is_err());
+        assert!(from_spec("trim=a").is_err());
+
+        // the pointer is unescaped like any other json pointer
+        let normalizer = from_spec("trim=/a~1b/c~0d").unwrap();
+        let path = Path::new(vec![
+            PathElem::Key("a/b".to_owned()),
+            PathElem::Key("c~d".to_owned()),
+        ]);
+        assert_eq!(normalizer.normalize(&path, &json!(" x")), Some(json!("x")));
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{DiffChange, DiffElem};
+use jsonpatch::{JsonPatch, Patch, PatchElem};
+
+/// Convert diff elements to the json patch (RFC 6902) operations that turn the left document
+/// into the right one, the operations are applied in the order of `diffs`.
+///
+/// Changes of the key order can not be expressed by a json patch and are skipped.
+pub fn diffs_to_patch(diffs: &[DiffElem]) -> JsonPatch {
+    let patches = diffs
+        .iter()
+        .filter_map(|diff| {
+            let patch = match &diff.diff {
+                DiffChange::Add(val) => Patch::Add(val.clone()),
+                DiffChange::Remove(_) => Patch::Remove,
+                DiffChange::Replace { new_val, .. } | DiffChange::Collapsed { new_val, .. } => {
+                    Patch::Replace(new_val.clone())
+                }
+                DiffChange::Rename { from_key } => {
+                    let from = diff.path.parent_path()?.clone_then_add_key(from_key);
+                    Patch::Move {
+                        from: from.to_json_pointer(),
+                    }
+                }
+                DiffChange::Move { from } => Patch::Move {
+                    from: from.to_json_pointer(),
+                },
+                DiffChange::Copy { from } => Patch::Copy {
+                    from: from.to_json_pointer(),
+                },
+                DiffChange::KeyOrder { .. } => return None,
+            };
+            Some(PatchElem::new(patch, diff.path.to_json_pointer()))
+        })
+        .collect();
+    JsonPatch { patches }
+}
@@ -0,0 +1,164 @@
+use crate::{diff_json_val, DiffChange, DiffElem, DiffOptions, Path};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Collect the leaves below `val`, the scalars and the empty arrays and objects, with their
+/// path relative to `val`.
+fn collect_leaves<'a>(val: &'a Value, path: &mut Path, leaves: &mut Vec<(Path, &'a Value)>) {
+    match val {
+        Value::Array(arr) if !arr.is_empty() => {
+            for (idx, v) in arr.iter().enumerate() {
+                path.push_idx(idx);
+                collect_leaves(v, path, leaves);
+                path.pop();
+            }
+        }
+        Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map.iter() {
+                path.push_key(k);
+                collect_leaves(v, path, leaves);
+                path.pop();
+            }
+        }
+        _ => leaves.push((path.clone(), val)),
+    }
+}
+
+/// Similarity of two values by their leaves: twice the number of leaves that have the same
+/// value at the same relative path, divided by the number of leaves of both values.
+///
+/// The result is between 0.0 (nothing in common) and 1.0 (equal).
+pub fn leaf_similarity(val0: &Value, val1: &Value) -> f64 {
+    let mut leaves0 = vec![];
+    let mut leaves1 = vec![];
+    collect_leaves(val0, &mut Path::empty(), &mut leaves0);
+    collect_leaves(val1, &mut Path::empty(), &mut leaves1);
+    let leaves1_map: HashMap<&Path, &Value> = leaves1.iter().map(|(p, v)| (p, *v)).collect();
+    let shared = leaves0
+        .iter()
+        .filter(|(p, v)| leaves1_map.get(p) == Some(v))
+        .count();
+    (2 * shared) as f64 / (leaves0.len() + leaves1.len()) as f64
+}
+
+/// A hash of `val`, equal values have the same hash whatever the order of their keys.
+pub(crate) fn value_hash(val: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match val {
+        Value::Null => 0u8.hash(&mut hasher),
+        Value::Bool(b) => (1u8, b).hash(&mut hasher),
+        Value::Number(n) => (2u8, n.to_string()).hash(&mut hasher),
+        Value::String(s) => (3u8, s).hash(&mut hasher),
+        Value::Array(arr) => {
+            4u8.hash(&mut hasher);
+            for v in arr {
+                value_hash(v).hash(&mut hasher);
+            }
+        }
+        Value::Object(map) => {
+            // the sum of the entries does not depend on their order
+            let entries = map.iter().fold(0u64, |sum, (k, v)| {
+                let mut entry_hasher = DefaultHasher::new();
+                (k, value_hash(v)).hash(&mut entry_hasher);
+                sum.wrapping_add(entry_hasher.finish())
+            });
+            (5u8, map.len(), entries).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Number of values in `val`, itself included.
+fn size(val: &Value) -> usize {
+    match val {
+        Value::Array(arr) => 1 + arr.iter().map(size).sum::<usize>(),
+        Value::Object(map) => 1 + map.values().map(size).sum::<usize>(),
+        _ => 1,
+    }
+}
+
+/// Number of values of both documents that a diff element changes.
+fn change_weight(diff: &DiffElem) -> usize {
+    match &diff.diff {
+        DiffChange::Add(val) | DiffChange::Remove(val) => size(val),
+        DiffChange::Replace { old_val, new_val } => size(old_val) + size(new_val),
+        DiffChange::Collapsed {
+            old_val, new_val, ..
+        } => size(old_val) + size(new_val),
+        // the value itself is kept, only its key or place changed on both sides
+        DiffChange::Rename { .. } | DiffChange::Move { .. } => 2,
+        DiffChange::Copy { .. } | DiffChange::KeyOrder { .. } => 1,
+    }
+}
+
+/// Similarity of two documents between 0.0 and 1.0 (equal): the share of the values of both
+/// documents that are not changed by their diff, so a change weighs as much as the subtree it
+/// replaces.
+pub fn similarity(val0: &Value, val1: &Value) -> f64 {
+    similarity_with_options(val0, val1, &DiffOptions::default())
+}
+
+/// `similarity` of the diff with `opts`.
+pub fn similarity_with_options(val0: &Value, val1: &Value, opts: &DiffOptions) -> f64 {
+    let total = size(val0) + size(val1);
+    let changed: usize = diff_json_val(val0, val1, opts)
+        .iter()
+        .map(change_weight)
+        .sum();
+    1.0 - (changed.min(total) as f64 / total as f64)
+}
+
+/// Distance of two documents between 0.0 (equal) and 1.0, `1 - similarity`.
+pub fn distance(val0: &Value, val1: &Value) -> f64 {
+    1.0 - similarity(val0, val1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::similarity::{distance, leaf_similarity, similarity, value_hash};
+    use serde_json::json;
+
+    #[test]
+    fn test_leaf_similarity() {
+        assert_eq!(leaf_similarity(&json!(1), &json!(1)), 1.0);
+        assert_eq!(leaf_similarity(&json!(1), &json!(2)), 0.0);
+        assert_eq!(leaf_similarity(&json!({}), &json!({})), 1.0);
+        assert_eq!(leaf_similarity(&json!({}), &json!([])), 0.0);
+        let v0 = json!({"name": "a", "age": 1, "tags": ["x", "y"]});
+        let v1 = json!({"name": "a", "age": 2, "tags": ["x", "y"]});
+        assert_eq!(leaf_similarity(&v0, &v1), 0.75);
+        let v2 = json!({"name": "a"});
+        assert_eq!(leaf_similarity(&v0, &v2), 0.4);
+    }
+
+    #[test]
+    fn test_value_hash() {
+        let v0 = json!({"a": [1, null, "x"], "b": {"c": true}});
+        let v1: serde_json::Value =
+            serde_json::from_str(r#"{"b": {"c": true}, "a": [1, null, "x"]}"#).unwrap();
+        assert_eq!(value_hash(&v0), value_hash(&v1));
+        assert_ne!(value_hash(&json!([1, 2])), value_hash(&json!([2, 1])));
+        assert_ne!(value_hash(&json!("1")), value_hash(&json!(1)));
+        assert_ne!(value_hash(&json!({"a": 1})), value_hash(&json!({"a": 2})));
+        assert_ne!(value_hash(&json!([[1], 2])), value_hash(&json!([[1, 2]])));
+    }
+
+    #[test]
+    fn test_similarity() {
+        let v0 = json!({"name": "a", "tags": ["x", "y"]});
+        assert_eq!(similarity(&v0, &v0), 1.0);
+        assert_eq!(distance(&v0, &v0), 0.0);
+        // 2 of the 10 values are replaced
+        let v1 = json!({"name": "b", "tags": ["x", "y"]});
+        assert_eq!(similarity(&v0, &v1), 0.8);
+        // one element is replaced and one removed, 3 of the 9 values
+        let v2 = json!({"name": "a", "tags": ["z"]});
+        assert_eq!(similarity(&v0, &v2), 1.0 - 3.0 / 9.0);
+        assert_eq!(similarity(&json!(1), &json!({"a": 1})), 0.0);
+        assert_eq!(distance(&json!(1), &json!({"a": 1})), 1.0);
+        // closer candidates score higher
+        assert!(similarity(&v0, &v1) > similarity(&v0, &v2));
+    }
+}
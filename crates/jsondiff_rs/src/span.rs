@@ -0,0 +1,236 @@
+//! Where the values of a json text are, so a change can be reported at its line and column.
+
+use crate::Path;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+/// The text of a value in a json source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// byte offset of the first character of the value
+    pub start: usize,
+    /// byte offset after the last character of the value
+    pub end: usize,
+    /// line of `start`, from 1
+    pub line: usize,
+    /// column of `start` in characters, from 1
+    pub col: usize,
+}
+
+/// The span of every value of a json text by its path.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    spans: HashMap<Path, Span>,
+}
+
+impl SourceMap {
+    /// Find the values of the json text `src`.
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut parser = Parser {
+            src,
+            pos: 0,
+            line_starts: std::iter::once(0)
+                .chain(src.match_indices('\n').map(|(idx, _)| idx + 1))
+                .collect(),
+            spans: HashMap::new(),
+        };
+        parser.value(&mut Path::empty())?;
+        parser.skip_ws();
+        if parser.pos < src.len() {
+            bail!("trailing characters at {}", parser.location(parser.pos));
+        }
+        Ok(Self {
+            spans: parser.spans,
+        })
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Span> {
+        self.spans.get(path)
+    }
+
+    /// The span of `path`, or of its closest ancestor in the source.
+    pub fn get_or_ancestor(&self, path: &Path) -> Option<&Span> {
+        let mut path = path.clone();
+        loop {
+            if let Some(span) = self.spans.get(&path) {
+                return Some(span);
+            }
+            path.pop()?;
+        }
+    }
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    // byte offset of the first character of every line
+    line_starts: Vec<usize>,
+    spans: HashMap<Path, Span>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn location(&self, pos: usize) -> String {
+        let (line, col) = self.line_col(pos);
+        format!("line {} column {}", line, col)
+    }
+
+    fn line_col(&self, pos: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|start| *start <= pos);
+        let col = self.src[self.line_starts[line - 1]..pos].chars().count() + 1;
+        (line, col)
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        self.skip_ws();
+        if self.peek() != Some(c) {
+            bail!(
+                "expected '{}' at {}",
+                c as char,
+                self.location(self.pos.min(self.src.len()))
+            );
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Skip a string and return its text with the escapes resolved.
+    fn string(&mut self) -> Result<String> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => bail!("unterminated string at {}", self.location(start)),
+            }
+        }
+        self.pos += 1;
+        serde_json::from_str(&self.src[start..self.pos])
+            .with_context(|| format!("invalid string at {}", self.location(start)))
+    }
+
+    fn value(&mut self, path: &mut Path) -> Result<()> {
+        self.skip_ws();
+        let start = self.pos;
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                self.skip_ws();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                } else {
+                    loop {
+                        self.skip_ws();
+                        let key = self.string()?;
+                        self.expect(b':')?;
+                        path.push_key(key);
+                        self.value(path)?;
+                        path.pop();
+                        self.skip_ws();
+                        match self.peek() {
+                            Some(b',') => self.pos += 1,
+                            _ => break self.expect(b'}')?,
+                        }
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                self.skip_ws();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                } else {
+                    let mut idx = 0;
+                    loop {
+                        path.push_idx(idx);
+                        self.value(path)?;
+                        path.pop();
+                        idx += 1;
+                        self.skip_ws();
+                        match self.peek() {
+                            Some(b',') => self.pos += 1,
+                            _ => break self.expect(b']')?,
+                        }
+                    }
+                }
+            }
+            Some(b'"') => {
+                self.string()?;
+            }
+            Some(_) => {
+                // a number or a literal, it ends at the next delimiter
+                while let Some(c) = self.peek() {
+                    if matches!(c, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r') {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    bail!("expected a value at {}", self.location(start));
+                }
+            }
+            None => bail!("expected a value at the end of the json"),
+        }
+        let (line, col) = self.line_col(start);
+        let span = Span {
+            start,
+            end: self.pos,
+            line,
+            col,
+        };
+        self.spans.insert(path.clone(), span);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::span::SourceMap;
+    use crate::Path;
+
+    #[test]
+    fn test_source_map() {
+        let src = "{\n  \"a\": [1, {\"b\\u0021\": null}],\n  \"ü\": \"x\"\n}\n";
+        let map = SourceMap::parse(src).unwrap();
+
+        let root = map.get(&Path::empty()).unwrap();
+        assert_eq!((root.start, root.line, root.col), (0, 1, 1));
+        assert_eq!(root.end, src.len() - 1);
+
+        let a = Path::empty().clone_then_add_key("a");
+        let a_span = map.get(&a).unwrap();
+        assert_eq!((a_span.line, a_span.col), (2, 8));
+        assert_eq!(&src[a_span.start..a_span.end], "[1, {\"b\\u0021\": null}]");
+
+        let b = a.clone_then_add_idx(1).clone_then_add_key("b!");
+        let b_span = map.get(&b).unwrap();
+        assert_eq!(&src[b_span.start..b_span.end], "null");
+        assert_eq!((b_span.line, b_span.col), (2, 24));
+
+        let u = Path::empty().clone_then_add_key("ü");
+        assert_eq!(map.get(&u).unwrap().col, 8);
+        assert_eq!(
+            map.get_or_ancestor(&u.clone_then_add_key("missing")),
+            map.get(&u)
+        );
+    }
+
+    #[test]
+    fn test_source_map_errors() {
+        assert!(SourceMap::parse("{\"a\": 1").is_err());
+        assert!(SourceMap::parse("[1,]").is_err());
+        assert!(SourceMap::parse("1 2").is_err());
+        assert!(SourceMap::parse("").is_err());
+    }
+}
@@ -0,0 +1,514 @@
+//! Tree edit distance between two json documents (Zhang–Shasha).
+//!
+//! Every json value is a node of an ordered tree, the members of an object and the elements of
+//! an array are its children. The label of a node is the key it is stored under (for object
+//! members), the type of the value and, for scalars, the value itself. An edit script deletes
+//! nodes (the children move up to the parent), inserts nodes and renames (relabels) nodes.
+
+use crate::{diff_json_arr_aligned, DiffChange, DiffElem, Path};
+use anyhow::{anyhow, Error, Result};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+const EPSILON: f64 = 1e-9;
+
+/// The cost of every kind of node edit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeEditCosts {
+    pub insert: f64,
+    pub delete: f64,
+    pub rename: f64,
+}
+
+impl Default for TreeEditCosts {
+    fn default() -> Self {
+        Self {
+            insert: 1.0,
+            delete: 1.0,
+            rename: 1.0,
+        }
+    }
+}
+
+impl FromStr for TreeEditCosts {
+    type Err = Error;
+
+    /// Parse `INSERT,DELETE,RENAME`, e.g. `1,1,2`.
+    fn from_str(s: &str) -> Result<Self> {
+        let costs = s
+            .split(',')
+            .map(|cost| cost.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()?;
+        match costs[..] {
+            [insert, delete, rename]
+                if costs.iter().all(|cost| cost.is_finite() && *cost >= 0.0) =>
+            {
+                Ok(Self {
+                    insert,
+                    delete,
+                    rename,
+                })
+            }
+            _ => Err(anyhow!(
+                "expect three finite non-negative costs INSERT,DELETE,RENAME, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// A node edit. Paths locate the node in the left document for `Delete`, in the right
+/// document for `Insert`, and in both for `Rename`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeEditOp {
+    Delete(Path),
+    Insert(Path),
+    Rename { from: Path, to: Path },
+}
+
+/// A minimal cost edit script.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeEdit {
+    pub cost: f64,
+    pub ops: Vec<TreeEditOp>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Label<'a> {
+    Scalar(&'a Value),
+    Array,
+    Object,
+}
+
+struct Node<'a> {
+    key: Option<&'a String>,
+    label: Label<'a>,
+    val: &'a Value,
+    path: Path,
+    // postorder index of the leftmost leaf below the node
+    leftmost: usize,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// The nodes of a json document in postorder.
+struct Tree<'a> {
+    nodes: Vec<Node<'a>>,
+    keyroots: Vec<usize>,
+}
+
+impl<'a> Tree<'a> {
+    fn new(root: &'a Value) -> Self {
+        let mut nodes = vec![];
+        Self::add(root, None, Path::empty(), &mut nodes);
+        let mut keyroots: HashMap<usize, usize> = HashMap::new();
+        for (idx, node) in nodes.iter().enumerate() {
+            // the highest node of every leftmost leaf
+            keyroots.insert(node.leftmost, idx);
+        }
+        let mut keyroots: Vec<usize> = keyroots.into_values().collect();
+        keyroots.sort_unstable();
+        Self { nodes, keyroots }
+    }
+
+    fn add(
+        val: &'a Value,
+        key: Option<&'a String>,
+        path: Path,
+        nodes: &mut Vec<Node<'a>>,
+    ) -> usize {
+        let mut children = vec![];
+        let label = match val {
+            Value::Array(arr) => {
+                for (idx, v) in arr.iter().enumerate() {
+                    children.push(Self::add(v, None, path.clone_then_add_idx(idx), nodes));
+                }
+                Label::Array
+            }
+            Value::Object(map) => {
+                for (k, v) in map.iter() {
+                    children.push(Self::add(v, Some(k), path.clone_then_add_key(k), nodes));
+                }
+                Label::Object
+            }
+            _ => Label::Scalar(val),
+        };
+        let idx = nodes.len();
+        let leftmost = children.first().map_or(idx, |child| nodes[*child].leftmost);
+        for child in children.iter() {
+            nodes[*child].parent = Some(idx);
+        }
+        nodes.push(Node {
+            key,
+            label,
+            val,
+            path,
+            leftmost,
+            parent: None,
+            children,
+        });
+        idx
+    }
+
+    fn root(&self) -> usize {
+        self.nodes.len() - 1
+    }
+
+    fn leftmost(&self, idx: usize) -> usize {
+        self.nodes[idx].leftmost
+    }
+}
+
+struct ZhangShasha<'a> {
+    tree0: Tree<'a>,
+    tree1: Tree<'a>,
+    costs: TreeEditCosts,
+    treedist: Vec<Vec<f64>>,
+}
+
+impl<'a> ZhangShasha<'a> {
+    fn new(val0: &'a Value, val1: &'a Value, costs: TreeEditCosts) -> Self {
+        let tree0 = Tree::new(val0);
+        let tree1 = Tree::new(val1);
+        let treedist = vec![vec![0.0; tree1.nodes.len()]; tree0.nodes.len()];
+        let mut zs = Self {
+            tree0,
+            tree1,
+            costs,
+            treedist,
+        };
+        for i in zs.tree0.keyroots.clone() {
+            for j in zs.tree1.keyroots.clone() {
+                zs.forestdist(i, j);
+            }
+        }
+        zs
+    }
+
+    fn rename_cost(&self, x: usize, y: usize) -> f64 {
+        let node0 = &self.tree0.nodes[x];
+        let node1 = &self.tree1.nodes[y];
+        if node0.key == node1.key && node0.label == node1.label {
+            0.0
+        } else {
+            self.costs.rename
+        }
+    }
+
+    /// The distances between the forests of the subtrees `i` and `j`, row `x - l(i) + 1` and
+    /// column `y - l(j) + 1` hold the distance of the forests up to the nodes `x` and `y`.
+    /// The tree distances of the subtrees with the same leftmost leaves are stored.
+    fn forestdist(&mut self, i: usize, j: usize) -> Vec<Vec<f64>> {
+        let li = self.tree0.leftmost(i);
+        let lj = self.tree1.leftmost(j);
+        let rows = i - li + 2;
+        let cols = j - lj + 2;
+        let mut fd = vec![vec![0.0; cols]; rows];
+        for row in 1..rows {
+            fd[row][0] = fd[row - 1][0] + self.costs.delete;
+        }
+        for col in 1..cols {
+            fd[0][col] = fd[0][col - 1] + self.costs.insert;
+        }
+        for row in 1..rows {
+            let x = li + row - 1;
+            let lx = self.tree0.leftmost(x);
+            for col in 1..cols {
+                let y = lj + col - 1;
+                let ly = self.tree1.leftmost(y);
+                let delete = fd[row - 1][col] + self.costs.delete;
+                let insert = fd[row][col - 1] + self.costs.insert;
+                if lx == li && ly == lj {
+                    let rename = fd[row - 1][col - 1] + self.rename_cost(x, y);
+                    fd[row][col] = delete.min(insert).min(rename);
+                    self.treedist[x][y] = fd[row][col];
+                } else {
+                    let subtree = fd[lx - li][ly - lj] + self.treedist[x][y];
+                    fd[row][col] = delete.min(insert).min(subtree);
+                }
+            }
+        }
+        fd
+    }
+
+    /// The pairs of mapped nodes and the edit script of a minimal edit.
+    fn mapping(&mut self) -> (Vec<(usize, usize)>, Vec<TreeEditOp>) {
+        let mut pairs = vec![];
+        let mut ops = vec![];
+        let mut subtrees = vec![(self.tree0.root(), self.tree1.root())];
+        while let Some((i, j)) = subtrees.pop() {
+            let fd = self.forestdist(i, j);
+            let li = self.tree0.leftmost(i);
+            let lj = self.tree1.leftmost(j);
+            let mut row = i + 1 - li;
+            let mut col = j + 1 - lj;
+            while row > 0 || col > 0 {
+                let dist = fd[row][col];
+                if row > 0 && col > 0 {
+                    let x = li + row - 1;
+                    let y = lj + col - 1;
+                    let lx = self.tree0.leftmost(x);
+                    let ly = self.tree1.leftmost(y);
+                    if lx == li && ly == lj {
+                        let rename_cost = self.rename_cost(x, y);
+                        if (dist - fd[row - 1][col - 1] - rename_cost).abs() < EPSILON {
+                            if rename_cost > 0.0 {
+                                ops.push(TreeEditOp::Rename {
+                                    from: self.tree0.nodes[x].path.clone(),
+                                    to: self.tree1.nodes[y].path.clone(),
+                                });
+                            }
+                            pairs.push((x, y));
+                            row -= 1;
+                            col -= 1;
+                            continue;
+                        }
+                    } else if (dist - fd[lx - li][ly - lj] - self.treedist[x][y]).abs() < EPSILON {
+                        subtrees.push((x, y));
+                        row = lx - li;
+                        col = ly - lj;
+                        continue;
+                    }
+                }
+                // fall back to a delete or an insert when no step matches the distance, e.g.
+                // when the costs are not finite
+                if row > 0
+                    && (col == 0 || (dist - fd[row - 1][col] - self.costs.delete).abs() < EPSILON)
+                {
+                    ops.push(TreeEditOp::Delete(
+                        self.tree0.nodes[li + row - 1].path.clone(),
+                    ));
+                    row -= 1;
+                } else {
+                    ops.push(TreeEditOp::Insert(
+                        self.tree1.nodes[lj + col - 1].path.clone(),
+                    ));
+                    col -= 1;
+                }
+            }
+        }
+        // backtracked from the last nodes
+        ops.reverse();
+        (pairs, ops)
+    }
+}
+
+/// The minimal cost to edit `val0` into `val1` and an edit script of that cost.
+pub fn tree_edit_distance(val0: &Value, val1: &Value, costs: &TreeEditCosts) -> TreeEdit {
+    let mut zs = ZhangShasha::new(val0, val1, *costs);
+    let (_, ops) = zs.mapping();
+    let cost = zs.treedist[zs.tree0.root()][zs.tree1.root()];
+    TreeEdit { cost, ops }
+}
+
+/// Turns a mapping of the nodes into diff elements.
+struct MappingDiff<'a> {
+    tree0: Tree<'a>,
+    tree1: Tree<'a>,
+    // node of the left tree -> mapped node of the right tree
+    mapped: HashMap<usize, usize>,
+}
+
+impl<'a> MappingDiff<'a> {
+    /// The node of `n1` that the child `c0` of `n0` is mapped to, if any.
+    fn mapped_child(&self, c0: usize, n1: usize) -> Option<usize> {
+        self.mapped
+            .get(&c0)
+            .filter(|c1| self.tree1.nodes[**c1].parent == Some(n1))
+            .copied()
+    }
+
+    fn replace(&self, n0: usize, n1: usize, mut diffs: Vec<DiffElem>, path: Path) -> Vec<DiffElem> {
+        let val0 = self.tree0.nodes[n0].val;
+        let val1 = self.tree1.nodes[n1].val;
+        if val0 != val1 {
+            diffs.push(DiffElem {
+                diff: DiffChange::Replace {
+                    old_val: val0.clone(),
+                    new_val: val1.clone(),
+                },
+                path,
+                location: None,
+            });
+        }
+        diffs
+    }
+
+    fn diff(&self, n0: usize, n1: usize, diffs: Vec<DiffElem>, path: Path) -> Vec<DiffElem> {
+        let node0 = &self.tree0.nodes[n0];
+        let node1 = &self.tree1.nodes[n1];
+        match (node0.val, node1.val) {
+            (Value::Object(m0), Value::Object(m1)) => self.diff_map(
+                n0,
+                n1,
+                m0.keys().collect(),
+                m1.keys().collect(),
+                diffs,
+                path,
+            ),
+            (Value::Array(arr0), Value::Array(arr1)) => {
+                let pairs = node0
+                    .children
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(idx0, c0)| {
+                        let c1 = self.mapped_child(*c0, n1)?;
+                        let idx1 = node1.children.iter().position(|c| *c == c1)?;
+                        Some((idx0, idx1))
+                    })
+                    .collect();
+                let diff_pair = |idx0: usize, idx1: usize, diffs, path| {
+                    self.diff(node0.children[idx0], node1.children[idx1], diffs, path)
+                };
+                diff_json_arr_aligned(arr0, arr1, pairs, true, diffs, path, diff_pair)
+            }
+            _ => self.replace(n0, n1, diffs, path),
+        }
+    }
+
+    fn diff_map(
+        &self,
+        n0: usize,
+        n1: usize,
+        keys0: Vec<&String>,
+        keys1: Vec<&String>,
+        mut diffs: Vec<DiffElem>,
+        path: Path,
+    ) -> Vec<DiffElem> {
+        let node0 = &self.tree0.nodes[n0];
+        let node1 = &self.tree1.nodes[n1];
+        let child1 = |key: &String| node1.children[keys1.iter().position(|k| *k == key).unwrap()];
+        // a member whose key changed is a rename when neither key is in both objects
+        let mut renamed_from = HashSet::new();
+        // new key -> (old key, left node, right node)
+        let mut renamed_to = HashMap::new();
+        for (key0, c0) in keys0.iter().zip(node0.children.iter()) {
+            if let Some(c1) = self.mapped_child(*c0, n1) {
+                let key1 = self.tree1.nodes[c1].key.unwrap();
+                if key1 != *key0 && !keys1.contains(key0) && !keys0.contains(&key1) {
+                    renamed_from.insert(*key0);
+                    renamed_to.insert(key1, (*key0, *c0, c1));
+                }
+            }
+        }
+
+        // changes and removals in the key order of the left object
+        for (key0, c0) in keys0.iter().zip(node0.children.iter()) {
+            let new_path = path.clone_then_add_key(key0);
+            if keys1.contains(key0) {
+                let c1 = child1(key0);
+                diffs = if self.mapped.get(c0) == Some(&c1) {
+                    self.diff(*c0, c1, diffs, new_path)
+                } else {
+                    self.replace(*c0, c1, diffs, new_path)
+                };
+            } else if !renamed_from.contains(key0) {
+                diffs.push(DiffElem {
+                    diff: DiffChange::Remove(self.tree0.nodes[*c0].val.clone()),
+                    path: new_path,
+                    location: None,
+                });
+            }
+        }
+
+        // additions and renames in the key order of the right object
+        for (key1, c1) in keys1.iter().zip(node1.children.iter()) {
+            if keys0.contains(key1) {
+                continue;
+            }
+            let new_path = path.clone_then_add_key(key1);
+            if let Some((from_key, c0, c1)) = renamed_to.get(key1) {
+                diffs.push(DiffElem {
+                    diff: DiffChange::Rename {
+                        from_key: from_key.to_string(),
+                    },
+                    path: new_path.clone(),
+                    location: None,
+                });
+                diffs = self.diff(*c0, *c1, diffs, new_path);
+            } else {
+                diffs.push(DiffElem {
+                    diff: DiffChange::Add(self.tree1.nodes[*c1].val.clone()),
+                    path: new_path,
+                    location: None,
+                });
+            }
+        }
+        diffs
+    }
+}
+
+/// Diff two documents along the mapping of a minimal tree edit. A node that is deleted or
+/// inserted between a mapped parent and its children can not be expressed as a json change,
+/// the values below it are replaced instead.
+pub(crate) fn diff(val0: &Value, val1: &Value, costs: &TreeEditCosts) -> Vec<DiffElem> {
+    let mut zs = ZhangShasha::new(val0, val1, *costs);
+    let (pairs, _) = zs.mapping();
+    let mapping_diff = MappingDiff {
+        mapped: pairs.into_iter().collect(),
+        tree0: zs.tree0,
+        tree1: zs.tree1,
+    };
+    let root0 = mapping_diff.tree0.root();
+    let root1 = mapping_diff.tree1.root();
+    if mapping_diff.mapped.get(&root0) == Some(&root1) {
+        mapping_diff.diff(root0, root1, vec![], Path::empty())
+    } else {
+        mapping_diff.replace(root0, root1, vec![], Path::empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tree_edit::{tree_edit_distance, TreeEditCosts, TreeEditOp};
+    use crate::{Path, PathElem};
+    use serde_json::json;
+
+    #[test]
+    fn test_tree_edit_distance() {
+        let costs = TreeEditCosts::default();
+        let cost = |v0, v1| tree_edit_distance(&v0, &v1, &costs).cost;
+        assert_eq!(cost(json!({"a": [1, 2]}), json!({"a": [1, 2]})), 0.0);
+        assert_eq!(cost(json!({"a": 1}), json!({"a": 2})), 1.0);
+        assert_eq!(cost(json!({"a": 1}), json!({"b": 1})), 1.0);
+        assert_eq!(cost(json!([1, 2, 3]), json!([1, 3])), 1.0);
+        assert_eq!(cost(json!([1, 2, 3]), json!([0, 1, 2, 3, 4])), 2.0);
+        // deleting the inner array moves its elements up
+        assert_eq!(cost(json!([[1, 2]]), json!([1, 2])), 1.0);
+        assert_eq!(cost(json!(1), json!({"a": 1, "b": 2})), 3.0);
+
+        let edit = tree_edit_distance(&json!({"a": 1, "b": 2}), &json!({"a": 1, "c": 3}), &costs);
+        let path = |key: &str| Path::new(vec![PathElem::Key(key.to_owned())]);
+        assert_eq!(
+            edit.ops,
+            vec![TreeEditOp::Rename {
+                from: path("b"),
+                to: path("c")
+            }]
+        );
+
+        // a rename that costs more than a delete and an insert
+        let costs: TreeEditCosts = "1,1,3".parse().unwrap();
+        let edit = tree_edit_distance(&json!({"a": 1}), &json!({"a": 2}), &costs);
+        assert_eq!(edit.cost, 2.0);
+        assert_eq!(
+            edit.ops,
+            vec![TreeEditOp::Insert(path("a")), TreeEditOp::Delete(path("a"))]
+        );
+        assert!("1,1".parse::<TreeEditCosts>().is_err());
+        assert!("1,-1,1".parse::<TreeEditCosts>().is_err());
+        assert!("inf,inf,1".parse::<TreeEditCosts>().is_err());
+        assert!("1,NaN,1".parse::<TreeEditCosts>().is_err());
+
+        // the backtrack does not underflow when the distances are not finite
+        let costs = TreeEditCosts {
+            insert: f64::INFINITY,
+            delete: f64::INFINITY,
+            rename: 1.0,
+        };
+        let edit = tree_edit_distance(&json!({"a": 1}), &json!({"a": 1, "b": 2}), &costs);
+        assert!(edit.cost.is_infinite());
+        assert!(edit.ops.contains(&TreeEditOp::Insert(path("b"))));
+    }
+}
@@ -0,0 +1,435 @@
+use anyhow::Result;
+use jsondiff_rs::formatters::{
+    ConsoleFormatter, DiffFormatter, DotFormatter, HtmlFormatter, LocationFormatter, LocationStyle,
+    MarkdownFormatter, RecordTable,
+};
+use jsondiff_rs::tree_edit::TreeEditCosts;
+use jsondiff_rs::{normalizer, DiffEngine, DiffOptions, JsonDiff, PathMode};
+use jsonptr::pointer::JsonPointer;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn read_case(case: &str) -> Result<(Value, Value)> {
+    let dir = golden_dir();
+    let left = fs::read_to_string(dir.join(format!("{}.left.json", case)))?;
+    let right = fs::read_to_string(dir.join(format!("{}.right.json", case)))?;
+    Ok((serde_json::from_str(&left)?, serde_json::from_str(&right)?))
+}
+
+/// Compare `output` with the golden file `tests/golden/<golden>`. Run the tests with
+/// `UPDATE_GOLDEN=1` to write the golden files instead.
+fn compare_golden(output: &str, golden: &str) -> Result<()> {
+    let golden_path = golden_dir().join(golden);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&golden_path, output)?;
+        return Ok(());
+    }
+    let expected = fs::read_to_string(&golden_path)?;
+    assert_eq!(output, expected, "output differs from {}", golden);
+    Ok(())
+}
+
+/// Render the diff of `tests/golden/<case>.left.json` and `<case>.right.json` and compare
+/// it with the golden file `tests/golden/<golden>`.
+fn check_golden(
+    case: &str,
+    opts: &DiffOptions,
+    formatter: &dyn DiffFormatter,
+    golden: &str,
+) -> Result<()> {
+    let (left, right) = read_case(case)?;
+    let json_diff = JsonDiff::diff_with_options(&left, &right, opts);
+    compare_golden(&formatter.format(&left, &json_diff), golden)
+}
+
+#[test]
+fn console_basic() -> Result<()> {
+    check_golden(
+        "basic",
+        &DiffOptions::default(),
+        &ConsoleFormatter::default(),
+        "basic.console.txt",
+    )
+}
+
+#[test]
+fn console_context() -> Result<()> {
+    check_golden(
+        "context",
+        &DiffOptions::default(),
+        &ConsoleFormatter { context: Some(1) },
+        "context.console.txt",
+    )
+}
+
+#[test]
+fn console_moves() -> Result<()> {
+    let opts = DiffOptions {
+        rename_threshold: Some(1.0),
+        detect_moves: true,
+        detect_copies: true,
+        ..Default::default()
+    };
+    check_golden(
+        "moves",
+        &opts,
+        &ConsoleFormatter::default(),
+        "moves.console.txt",
+    )
+}
+
+#[test]
+fn console_lowercase_keys() -> Result<()> {
+    let opts = DiffOptions {
+        normalizers: vec![normalizer::from_spec("lowercase-keys")?],
+        ..Default::default()
+    };
+    check_golden(
+        "lowercase_keys",
+        &opts,
+        &ConsoleFormatter::default(),
+        "lowercase_keys.console.txt",
+    )
+}
+
+#[test]
+fn html_basic() -> Result<()> {
+    check_golden(
+        "basic",
+        &DiffOptions::default(),
+        &HtmlFormatter::default(),
+        "basic.html",
+    )
+}
+
+#[test]
+fn html_moves() -> Result<()> {
+    let opts = DiffOptions {
+        rename_threshold: Some(1.0),
+        detect_moves: true,
+        detect_copies: true,
+        ..Default::default()
+    };
+    let formatter = HtmlFormatter {
+        collapse_unchanged: true,
+    };
+    check_golden("moves", &opts, &formatter, "moves.html")
+}
+
+#[test]
+fn markdown_basic() -> Result<()> {
+    let formatter = MarkdownFormatter {
+        max_value_len: 12,
+        ..Default::default()
+    };
+    check_golden("basic", &DiffOptions::default(), &formatter, "basic.md")
+}
+
+#[test]
+fn markdown_moves() -> Result<()> {
+    let opts = DiffOptions {
+        rename_threshold: Some(1.0),
+        detect_moves: true,
+        detect_copies: true,
+        ..Default::default()
+    };
+    let formatter = MarkdownFormatter {
+        max_rows: Some(2),
+        diff_block: true,
+        context: Some(0),
+        ..Default::default()
+    };
+    check_golden("moves", &opts, &formatter, "moves.md")
+}
+
+#[test]
+fn dot_basic() -> Result<()> {
+    check_golden("basic", &DiffOptions::default(), &DotFormatter, "basic.dot")
+}
+
+#[test]
+fn dot_moves() -> Result<()> {
+    let opts = DiffOptions {
+        rename_threshold: Some(1.0),
+        detect_moves: true,
+        detect_copies: true,
+        ..Default::default()
+    };
+    check_golden("moves", &opts, &DotFormatter, "moves.dot")
+}
+
+#[test]
+fn dot_collapsed() -> Result<()> {
+    let opts = DiffOptions {
+        max_depth: Some(1),
+        ..Default::default()
+    };
+    check_golden("collapsed", &opts, &DotFormatter, "collapsed.dot")
+}
+
+#[test]
+fn console_collapsed() -> Result<()> {
+    let opts = DiffOptions {
+        max_depth: Some(1),
+        ..Default::default()
+    };
+    check_golden(
+        "collapsed",
+        &opts,
+        &ConsoleFormatter::default(),
+        "collapsed.console.txt",
+    )
+}
+
+#[test]
+fn console_collapsed_rename() -> Result<()> {
+    let left: Value = serde_json::from_str(r#"{"owner": {"id": 7, "role": "admin"}}"#)?;
+    let right: Value = serde_json::from_str(r#"{"user": {"id": 7, "role": "editor"}}"#)?;
+    let opts = DiffOptions {
+        max_depth: Some(1),
+        rename_threshold: Some(0.5),
+        ..Default::default()
+    };
+    let json_diff = JsonDiff::diff_with_options(&left, &right, &opts);
+    let output = ConsoleFormatter::default().format(&left, &json_diff);
+    assert_eq!(
+        output,
+        "  {\n~     (renamed) owner -> user\n~     user: { … 1 change }\n  }\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn record_table() -> Result<()> {
+    let (left, right) = read_case("records")?;
+    let table = RecordTable::compare(&left, &right, &JsonPointer::new("/id")?)?;
+    compare_golden(
+        &table.format_text(|_, text| text.to_owned()),
+        "records.table.txt",
+    )?;
+    compare_golden(&table.to_csv(), "records.csv")
+}
+
+fn check_locations(
+    case: &str,
+    opts: &DiffOptions,
+    style: LocationStyle,
+    golden: &str,
+) -> Result<()> {
+    let dir = golden_dir();
+    let left_src = fs::read_to_string(dir.join(format!("{}.left.json", case)))?;
+    let right_src = fs::read_to_string(dir.join(format!("{}.right.json", case)))?;
+    let left: Value = serde_json::from_str(&left_src)?;
+    let right: Value = serde_json::from_str(&right_src)?;
+    let json_diff = JsonDiff::diff_with_options(&left, &right, opts);
+    let formatter =
+        LocationFormatter::new("left.json", &left_src, "right.json", &right_src, style)?;
+    compare_golden(&formatter.format(&left, &json_diff), golden)
+}
+
+/// Documents whose changed values are in the same order in both, with or without the
+/// `preserve_order` feature: changes and removals follow the left document, additions the
+/// right document.
+const ORDER_LEFT: &str = r#"{
+  "a": "a-old",
+  "b": ["keep", "b-removed", "keep2"],
+  "c": {"w": "keep", "y": "y-removed", "z": "keep"},
+  "d": "keep"
+}"#;
+const ORDER_RIGHT: &str = r#"{
+  "a": "a-new",
+  "b": ["keep", "keep2", "b-added"],
+  "c": {"w": "keep", "z": "keep", "zz": "z-added"},
+  "d": "keep",
+  "e": "e-added"
+}"#;
+const ORDER_MARKERS: [&str; 7] = [
+    "a-old",
+    "a-new",
+    "b-removed",
+    "b-added",
+    "y-removed",
+    "z-added",
+    "e-added",
+];
+
+/// Assert that every marker is in `output`, each one after the one before it.
+fn assert_in_order(output: &str, markers: &[&str], name: &str) {
+    let positions: Vec<usize> = markers
+        .iter()
+        .map(|marker| {
+            output
+                .find(marker)
+                .unwrap_or_else(|| panic!("{} output has no {}:\n{}", name, marker, output))
+        })
+        .collect();
+    assert!(
+        positions.windows(2).all(|pair| pair[0] < pair[1]),
+        "{} output is not in document order:\n{}",
+        name,
+        output
+    );
+}
+
+#[test]
+fn formatters_keep_document_order() -> Result<()> {
+    let left: Value = serde_json::from_str(ORDER_LEFT)?;
+    let right: Value = serde_json::from_str(ORDER_RIGHT)?;
+    let json_diff = JsonDiff::diff(&left, &right);
+    let formatters: [(&str, Box<dyn DiffFormatter>); 5] = [
+        ("console", Box::new(ConsoleFormatter::default())),
+        ("html", Box::new(HtmlFormatter::default())),
+        ("markdown", Box::new(MarkdownFormatter::default())),
+        ("dot", Box::new(DotFormatter)),
+        (
+            "locations",
+            Box::new(LocationFormatter::new(
+                "left.json",
+                ORDER_LEFT,
+                "right.json",
+                ORDER_RIGHT,
+                LocationStyle::Plain,
+            )?),
+        ),
+    ];
+    for (name, formatter) in formatters.iter() {
+        let output = formatter.format(&left, &json_diff);
+        assert_in_order(&output, &ORDER_MARKERS, name);
+        // the same documents always render the same
+        let again = formatter.format(&left, &JsonDiff::diff(&left, &right));
+        assert_eq!(output, again, "{} output is not stable", name);
+    }
+
+    let patch = serde_json::to_value(json_diff.to_patch())?;
+    let paths: Vec<&str> = patch
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|op| op["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(paths, ["/a", "/b/1", "/b/2", "/c/y", "/c/zz", "/e"]);
+    Ok(())
+}
+
+#[test]
+fn formatters_render_another_left_document() -> Result<()> {
+    let left: Value = serde_json::from_str(ORDER_LEFT)?;
+    let right: Value = serde_json::from_str(ORDER_RIGHT)?;
+    let json_diff = JsonDiff::diff(&left, &right);
+    let ptr = JsonPointer::new("/b")?;
+    let sub_diff = JsonDiff::diff_at(
+        &left,
+        &right,
+        &ptr,
+        &DiffOptions::default(),
+        PathMode::Absolute,
+    )?;
+    let formatters: [(&str, Box<dyn DiffFormatter>); 4] = [
+        ("console", Box::new(ConsoleFormatter::default())),
+        ("html", Box::new(HtmlFormatter::default())),
+        ("markdown", Box::new(MarkdownFormatter::default())),
+        ("dot", Box::new(DotFormatter)),
+    ];
+    for (name, formatter) in formatters.iter() {
+        // the removed array element is not in this left document, it is still listed
+        let other: Value = serde_json::from_str(r#"{"b": [], "c": 1}"#)?;
+        let output = formatter.format(&other, &json_diff);
+        assert!(output.contains("b-removed"), "{} output:\n{}", name, output);
+        // the value at the root of an absolute diff instead of the whole document
+        let output = formatter.format(&left["b"], &sub_diff);
+        assert!(output.contains("b-added"), "{} output:\n{}", name, output);
+    }
+    Ok(())
+}
+
+#[test]
+fn formatters_show_renamed_and_changed_keys() -> Result<()> {
+    let left: Value = serde_json::from_str(
+        r#"{"owner": {"id": 7, "name": "bob", "role": "admin"}, "size": 1, "x": "old"}"#,
+    )?;
+    let right: Value = serde_json::from_str(
+        r#"{"size": 1, "user": {"id": 7, "name": "bob", "role": "editor"}, "y": "new"}"#,
+    )?;
+    let opts = DiffOptions {
+        rename_threshold: Some(0.5),
+        ..Default::default()
+    };
+    // the tree edit engine renames the scalar and changes its value at the same path
+    let tree_edit_opts = DiffOptions {
+        engine: DiffEngine::TreeEdit(TreeEditCosts::default()),
+        ..Default::default()
+    };
+    let formatters: [(&str, Box<dyn DiffFormatter>, [&str; 2]); 4] = [
+        (
+            "console",
+            Box::new(ConsoleFormatter::default()),
+            ["(renamed) owner -> user", "(renamed) x -> y"],
+        ),
+        (
+            "html",
+            Box::new(HtmlFormatter::default()),
+            [
+                "<del>owner</del> <ins>user</ins>",
+                "<del>x</del> <ins>y</ins>",
+            ],
+        ),
+        (
+            "markdown",
+            Box::new(MarkdownFormatter::default()),
+            [
+                "| `/user` | renamed | `owner` |",
+                "| `/y` | renamed | `x` |",
+            ],
+        ),
+        ("dot", Box::new(DotFormatter), ["owner → user", "x → y"]),
+    ];
+    for (name, formatter, [renamed_object, renamed_scalar]) in formatters.iter() {
+        let output = formatter.format(&left, &JsonDiff::diff_with_options(&left, &right, &opts));
+        assert!(
+            output.contains(renamed_object),
+            "{} output:\n{}",
+            name,
+            output
+        );
+        assert!(output.contains("editor"), "{} output:\n{}", name, output);
+
+        let left: Value = serde_json::from_str(r#"{"x": "old"}"#)?;
+        let right: Value = serde_json::from_str(r#"{"y": "new"}"#)?;
+        let json_diff = JsonDiff::diff_with_options(&left, &right, &tree_edit_opts);
+        let output = formatter.format(&left, &json_diff);
+        assert!(
+            output.contains(renamed_scalar),
+            "{} output:\n{}",
+            name,
+            output
+        );
+        assert!(output.contains("new"), "{} output:\n{}", name, output);
+    }
+    Ok(())
+}
+
+#[test]
+fn locations_basic() -> Result<()> {
+    check_locations(
+        "basic",
+        &DiffOptions::default(),
+        LocationStyle::Plain,
+        "basic.locations.txt",
+    )
+}
+
+#[test]
+fn locations_github() -> Result<()> {
+    let opts = DiffOptions {
+        rename_threshold: Some(1.0),
+        detect_moves: true,
+        detect_copies: true,
+        ..Default::default()
+    };
+    check_locations("moves", &opts, LocationStyle::GitHub, "moves.github.txt")
+}
@@ -1,5 +1,5 @@
 // use crate::{Path, PathElem};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use std::convert::TryFrom;
 use thiserror::Error;
@@ -13,10 +13,24 @@ use jsonptr::pointer::JsonPointer;
 struct Operation {
     op: String,
     path: String,
+    // `"value": null` is a value, only a missing field is `None`
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
     value: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     from: Option<String>,
 }
 
+fn deserialize_some<'de, D>(deserializer: D) -> std::result::Result<Option<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Value::deserialize(deserializer).map(Some)
+}
+
 impl From<PatchElem> for Operation {
     fn from(patch_elem: PatchElem) -> Self {
         let ptr = patch_elem.json_ptr;
@@ -34,19 +48,19 @@ impl From<PatchElem> for Operation {
                 from: None,
             },
             Patch::Replace(val) => Operation {
-                op: "move".to_string(),
+                op: "replace".to_string(),
                 path: ptr.to_escaped_string(),
                 value: Some(val),
                 from: None,
             },
             Patch::Move { from } => Operation {
-                op: "from".to_string(),
+                op: "move".to_string(),
                 path: ptr.to_escaped_string(),
                 value: None,
                 from: Some(from.to_escaped_string()),
             },
             Patch::Copy { from } => Operation {
-                op: "from".to_string(),
+                op: "copy".to_string(),
                 path: ptr.to_escaped_string(),
                 value: None,
                 from: Some(from.to_escaped_string()),
@@ -115,7 +129,7 @@ impl TryInto<PatchElem> for Operation {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Patch {
     Add(Value),
     Remove,
@@ -125,12 +139,26 @@ pub enum Patch {
     Test(Value),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PatchElem {
     patch: Patch,
     json_ptr: JsonPointer,
 }
 
+impl PatchElem {
+    pub fn new(patch: Patch, json_ptr: JsonPointer) -> Self {
+        PatchElem { patch, json_ptr }
+    }
+
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    pub fn json_ptr(&self) -> &JsonPointer {
+        &self.json_ptr
+    }
+}
+
 impl TryFrom<&str> for PatchElem {
     type Error = anyhow::Error;
 
@@ -172,6 +200,16 @@ impl TryFrom<&str> for JsonPatch {
     }
 }
 
+impl Serialize for JsonPatch {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let ops: Vec<Operation> = self.patches.iter().cloned().map(Operation::from).collect();
+        ops.serialize(serializer)
+    }
+}
+
 impl TryFrom<String> for JsonPatch {
     type Error = anyhow::Error;
 
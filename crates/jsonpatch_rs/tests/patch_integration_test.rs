@@ -89,6 +89,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn add_and_replace_a_null_value() -> Result<()> {
+        let data = r#"{ "foo": "bar"}"#;
+        let patches_str = r#"
+            [
+                { "op": "add", "path": "/baz", "value": null },
+                { "op": "replace", "path": "/foo", "value": null }
+            ]"#;
+        let expected_str = r#"{ "baz": null, "foo": null }"#;
+        test_json_patch_arr(data, patches_str, expected_str)?;
+        assert!(JsonPatch::try_from(r#"[{ "op": "add", "path": "/baz" }]"#).is_err());
+        Ok(())
+    }
+
     #[test]
     fn add_a_nested_member_object() -> Result<()> {
         let data = r#"{ "foo": "bar"}"#;
@@ -382,6 +396,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::cmp_owned)]
     fn test_a_value_error() -> Result<()> {
         let data = r#"{ "baz": "qux" }"#;
         let patches_str = r#"
@@ -431,6 +446,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(unused_variables)]
     fn add_to_nonexistent_target() -> Result<()> {
         let data = r#"{ "foo": "bar" }"#;
         let patch_str = r#"
@@ -443,4 +459,24 @@ mod tests {
             Err(e) => Ok(()),
         }
     }
+
+    #[cfg(feature = "preserve_order")]
+    #[test]
+    fn keep_key_order() -> Result<()> {
+        let data = r#"{ "zeta": 1, "beta": 2, "alpha": 3, "gamma": 4 }"#;
+        let patch_str = r#"
+            [
+                { "op": "remove", "path": "/beta" },
+                { "op": "replace", "path": "/zeta", "value": 0 },
+                { "op": "add", "path": "/delta", "value": 5 }
+            ]
+            "#;
+        let jp: JsonPatch = JsonPatch::try_from(patch_str)?;
+        let res = jp.apply(&serde_json::from_str(data)?)?;
+        assert_eq!(
+            res.to_string(),
+            r#"{"zeta":0,"alpha":3,"gamma":4,"delta":5}"#
+        );
+        Ok(())
+    }
 }
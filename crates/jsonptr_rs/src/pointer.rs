@@ -55,7 +55,7 @@ impl JsonPointer {
     }
 
     pub fn get_mut<'a>(&self, val: &'a mut Value) -> Result<ValueMutRef<'a>> {
-        if self.len() == 0 {
+        if self.is_empty() {
             return Ok(ValueMutRef::Root(val));
         }
 
@@ -113,7 +113,7 @@ impl JsonPointer {
             .iter()
             .map(|token| token.to_escaped_string())
             .collect();
-        if path_token_strs.len() == 0 {
+        if path_token_strs.is_empty() {
             return String::from("");
         }
         format!("/{}", path_token_strs.join("/"))
@@ -122,7 +122,7 @@ impl JsonPointer {
 
 impl JsonPointer {
     fn parse(s: &str) -> Result<Self> {
-        if s == "" {
+        if s.is_empty() {
             // Empty tokens
             return Ok(JsonPointer { tokens: vec![] });
         }
@@ -132,7 +132,7 @@ impl JsonPointer {
         let tokens = s
             .split('/')
             .skip(1) // skip for first leaing empty elem
-            .map(|s| Token::new(s))
+            .map(Token::new)
             .collect::<Vec<Token>>();
 
         Ok(JsonPointer { tokens })
@@ -141,6 +141,11 @@ impl JsonPointer {
     pub fn new(s: &str) -> Result<Self> {
         Self::parse(s)
     }
+
+    /// Append a reference token, `token` is the raw key or index, not escaped.
+    pub fn push<T: ToString>(&mut self, token: T) {
+        self.tokens.push(Token::from_unescaped(&token.to_string()))
+    }
 }
 
 impl TryFrom<&str> for JsonPointer {
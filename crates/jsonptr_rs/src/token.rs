@@ -18,11 +18,9 @@ impl Token {
         if self.val == "-" {
             return Some(TokenIndex::IndexAfterLastElem);
         }
-        if self.val.len() != 1 {
-            if self.val.trim_start_matches('0').len() != self.val.len() {
-                // Leading zero
-                return None;
-            }
+        if self.val.len() != 1 && self.val.trim_start_matches('0').len() != self.val.len() {
+            // Leading zero
+            return None;
         }
         if let Ok(index) = self.val.parse::<usize>() {
             Some(TokenIndex::Index(index))
@@ -51,6 +49,11 @@ impl Token {
         s.replace('~', "~0").replace('/', "~1")
     }
 
+    /// Token of a raw key or index, which is not escaped.
+    pub fn from_unescaped(s: &str) -> Self {
+        Token { val: s.to_owned() }
+    }
+
     pub fn new(s: &str) -> Self {
         Token {
             val: Self::unescape(s),
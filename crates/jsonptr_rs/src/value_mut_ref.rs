@@ -93,7 +93,7 @@ impl<'a> ValueMutRef<'a> {
                         }
                     }
                     TokenIndex::IndexAfterLastElem => {
-                        if parent.len() != 0 {
+                        if !parent.is_empty() {
                             Ok(parent.pop().unwrap())
                         } else {
                             // TODO: do not know if arr is empty what todo, maybe need raise error here
@@ -104,7 +104,7 @@ impl<'a> ValueMutRef<'a> {
             }
             ValueMutRef::ObjElem { parent, key } => {
                 if parent.contains_key(&key) {
-                    let v = parent.remove(&key).unwrap();
+                    let v = Self::remove_key(parent, &key);
                     Ok(v)
                 } else {
                     Err(anyhow!("key {} not exist", key))
@@ -114,6 +114,16 @@ impl<'a> ValueMutRef<'a> {
         }
     }
 
+    #[cfg(not(feature = "preserve_order"))]
+    fn remove_key(parent: &mut Map<String, Value>, key: &str) -> Value {
+        parent.remove(key).unwrap()
+    }
+
+    #[cfg(feature = "preserve_order")]
+    fn remove_key(parent: &mut Map<String, Value>, key: &str) -> Value {
+        parent.shift_remove(key).unwrap()
+    }
+
     pub fn get(&self) -> Option<&Value> {
         match self {
             ValueMutRef::ArrayElem { parent, idx } => match idx {
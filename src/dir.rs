@@ -0,0 +1,118 @@
+//! Pair the json files of two directory trees by their path relative to the directories.
+
+use anyhow::{bail, Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// The files that are compared when no `--include` glob is given.
+const DEFAULT_INCLUDE: &str = "*.json";
+
+/// A file of either directory and where it is in both.
+pub struct FilePair {
+    /// path relative to the directories, with `/` separators
+    pub rel_path: String,
+    pub left: Option<PathBuf>,
+    pub right: Option<PathBuf>,
+}
+
+/// Which files of the directories are compared, by globs on their relative paths.
+pub struct FileFilter {
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+fn glob_set(globs: &[&str]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(Glob::new(glob).with_context(|| format!("invalid glob `{}`", glob))?);
+    }
+    Ok(builder.build()?)
+}
+
+impl FileFilter {
+    /// A file is compared when it matches one of `include` (`*.json` if empty) and none of
+    /// `exclude`. `*` also matches `/`, so `*.json` matches the json files in subdirectories.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let include: Vec<&str> = if include.is_empty() {
+            vec![DEFAULT_INCLUDE]
+        } else {
+            include.iter().map(|glob| glob.as_str()).collect()
+        };
+        let exclude: Vec<&str> = exclude.iter().map(|glob| glob.as_str()).collect();
+        Ok(Self {
+            include: glob_set(&include)?,
+            exclude: glob_set(&exclude)?,
+        })
+    }
+
+    pub fn matches(&self, rel_path: &str) -> bool {
+        self.include.is_match(rel_path) && !self.exclude.is_match(rel_path)
+    }
+}
+
+/// The files below `dir` that pass `filter`, by their relative path.
+fn list_files(dir: &Path, filter: &FileFilter) -> Result<BTreeMap<String, PathBuf>> {
+    if !dir.is_dir() {
+        bail!("{} is not a directory", dir.display());
+    }
+    let mut files = BTreeMap::new();
+    for entry in WalkDir::new(dir) {
+        let entry = entry.with_context(|| format!("can not read {}", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel_path = entry
+            .path()
+            .strip_prefix(dir)?
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if filter.matches(&rel_path) {
+            files.insert(rel_path, entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+/// The files of both directories that pass `filter`, sorted by their relative path.
+pub fn pair_files(left_dir: &Path, right_dir: &Path, filter: &FileFilter) -> Result<Vec<FilePair>> {
+    let mut left_files = list_files(left_dir, filter)?;
+    let mut right_files = list_files(right_dir, filter)?;
+    let mut rel_paths: Vec<String> = left_files
+        .keys()
+        .chain(right_files.keys())
+        .cloned()
+        .collect();
+    rel_paths.sort();
+    rel_paths.dedup();
+    Ok(rel_paths
+        .into_iter()
+        .map(|rel_path| FilePair {
+            left: left_files.remove(&rel_path),
+            right: right_files.remove(&rel_path),
+            rel_path,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dir::FileFilter;
+
+    #[test]
+    fn test_file_filter() {
+        let filter = FileFilter::new(&[], &["fixtures/tmp/**".to_owned()]).unwrap();
+        assert!(filter.matches("a.json"));
+        assert!(filter.matches("fixtures/b.json"));
+        assert!(!filter.matches("fixtures/tmp/c.json"));
+        assert!(!filter.matches("README.md"));
+
+        let filter = FileFilter::new(&["users/*.json".to_owned()], &[]).unwrap();
+        assert!(filter.matches("users/1.json"));
+        assert!(!filter.matches("orders/1.json"));
+        assert!(FileFilter::new(&["[".to_owned()], &[]).is_err());
+    }
+}
@@ -0,0 +1,138 @@
+//! Use the diff from git: as the program of `GIT_EXTERNAL_DIFF` or a diff driver, which git calls
+//! with `path old-file old-hex old-mode new-file new-hex new-mode`, followed by `new-path` and
+//! the rename message for a renamed or copied file, and as a textconv filter.
+
+use serde_json::{Map, Value};
+
+/// The file git passes for the missing side of an added or deleted file.
+const NULL_FILE: &str = "/dev/null";
+
+/// Whether `file` is the missing side of an added or deleted file.
+pub fn is_null_file(file: &str) -> bool {
+    file == NULL_FILE
+}
+
+/// The header git prints in front of the diff of a file. `new_path` differs from `path` for
+/// a renamed or copied file, then `rename_msg` holds the lines git describes it with.
+pub fn header(
+    path: &str,
+    new_path: &str,
+    old_file: &str,
+    old_mode: &str,
+    new_file: &str,
+    new_mode: &str,
+    rename_msg: Option<&str>,
+) -> Vec<String> {
+    let mut lines = vec![format!("diff --git a/{} b/{}", path, new_path)];
+    if is_null_file(old_file) {
+        lines.push(format!("new file mode {}", new_mode));
+    } else if is_null_file(new_file) {
+        lines.push(format!("deleted file mode {}", old_mode));
+    } else if old_mode != new_mode {
+        lines.push(format!("old mode {}", old_mode));
+        lines.push(format!("new mode {}", new_mode));
+    }
+    if let Some(msg) = rename_msg {
+        lines.extend(
+            msg.lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_owned()),
+        );
+    }
+    let side = |file: &str, path: &str| {
+        if is_null_file(file) {
+            NULL_FILE.to_owned()
+        } else {
+            path.to_owned()
+        }
+    };
+    lines.push(format!("--- {}", side(old_file, &format!("a/{}", path))));
+    lines.push(format!(
+        "+++ {}",
+        side(new_file, &format!("b/{}", new_path))
+    ));
+    lines
+}
+
+/// `val` with the keys of all objects sorted, whatever order the json map keeps.
+pub fn canonical(val: Value) -> Value {
+    match val {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|(k0, _), (k1, _)| k0.cmp(k1));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, val)| (key, canonical(val)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(arr) => Value::Array(arr.into_iter().map(canonical).collect()),
+        val => val,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::git::{canonical, header};
+    use serde_json::json;
+
+    #[test]
+    fn test_canonical() {
+        let val: serde_json::Value =
+            serde_json::from_str(r#"{"b": [{"d": 1, "c": 2}], "a": null}"#).unwrap();
+        let text = serde_json::to_string(&canonical(val)).unwrap();
+        assert_eq!(text, r#"{"a":null,"b":[{"c":2,"d":1}]}"#);
+        assert_eq!(canonical(json!([3, 1])), json!([3, 1]));
+    }
+
+    #[test]
+    fn test_header() {
+        assert_eq!(
+            header("a.json", "a.json", "/tmp/x", "100644", "a.json", "100755", None),
+            [
+                "diff --git a/a.json b/a.json",
+                "old mode 100644",
+                "new mode 100755",
+                "--- a/a.json",
+                "+++ b/a.json"
+            ]
+        );
+        assert_eq!(
+            header(
+                "a.json",
+                "a.json",
+                "/dev/null",
+                ".",
+                "a.json",
+                "100644",
+                None
+            ),
+            [
+                "diff --git a/a.json b/a.json",
+                "new file mode 100644",
+                "--- /dev/null",
+                "+++ b/a.json"
+            ]
+        );
+        assert_eq!(
+            header(
+                "a.json",
+                "b.json",
+                "/tmp/x",
+                "100644",
+                "b.json",
+                "100644",
+                Some("similarity index 90%\nrename from a.json\nrename to b.json\n")
+            ),
+            [
+                "diff --git a/a.json b/b.json",
+                "similarity index 90%",
+                "rename from a.json",
+                "rename to b.json",
+                "--- a/a.json",
+                "+++ b/b.json"
+            ]
+        );
+    }
+}
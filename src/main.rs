@@ -1,260 +1,257 @@
-use anyhow::Result;
-use clap::{Parser, Subcommand};
-use console::Style;
-use jsondiff::DiffChange;
+mod dir;
+mod git;
+mod watch;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use console::{pad_str, Alignment, Style, Term};
+use jsondiff::formatters::{
+    ConsoleFormatter, DiffFormatter, DotFormatter, HtmlFormatter, LocationFormatter, LocationStyle,
+    MarkdownFormatter, RecordTable,
+};
+use jsondiff::normalizer;
+use jsondiff::similarity;
+use jsondiff::ArrDiffMethod;
+use jsondiff::DiffEngine;
+use jsondiff::DiffOptions;
 use jsondiff::JsonDiff;
-use jsondiff::Path;
+use jsondiff::PathMode;
 use jsonpatch::JsonPatch;
+use jsonptr::pointer::JsonPointer;
 use serde_json::Value;
 use std::fs;
-use std::process;
-
-const INDENT_SIZE: usize = 4;
-
-fn format_json_val<F>(
-    jval: &Value,
-    key: Option<String>,
-    indent_count: usize,
-    diff_op: Option<&str>,
-    output: &mut F,
-) where
-    F: FnMut(&str, &str),
-{
-    let diff_op_s = diff_op.unwrap_or(" ");
-    let prefix = if let Some(key) = key {
-        format!("{}{}{}: ", diff_op_s, " ".repeat(indent_count), key)
-    } else {
-        format!("{}{}", diff_op_s, " ".repeat(indent_count))
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Width of the side by side output when it is not written to a terminal.
+const DEFAULT_WIDTH: usize = 160;
+
+fn style_line(diff_op: &str, line: &str) -> String {
+    match diff_op {
+        "+" => format!("{}", Style::new().green().apply_to(line)),
+        "-" => format!("{}", Style::new().red().apply_to(line)),
+        "~" => format!("{}", Style::new().yellow().apply_to(line)),
+        _ => line.to_owned(),
+    }
+}
+
+/// Lay the lines of the tree output out in two columns of the left and the right json, the
+/// removed lines of a change face the added lines.
+fn format_side_by_side(lines: &[(String, String)], width: usize) -> Vec<String> {
+    let col_width = width.saturating_sub(3) / 2;
+    // drop the column of the diff op, the columns show where a line belongs
+    let text = |line: &str| {
+        let line = line.get(1..).unwrap_or_default();
+        pad_str(line, col_width, Alignment::Left, Some("…")).into_owned()
     };
-    match jval {
-        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {
-            output(diff_op_s, &format!("{}{}", prefix, jval))
-        }
-        Value::Array(arr) => {
-            let left_bracket = format!("{}{}", prefix, "[");
-            output(diff_op_s, &left_bracket);
-            arr.iter().for_each(|v| {
-                format_json_val(v, None, indent_count + INDENT_SIZE, diff_op, output)
-            });
-            let right_bracket = format!("{}{}", " ".repeat(indent_count), "]");
-            output(diff_op_s, &right_bracket)
-        }
-        Value::Object(vmap) => {
-            let left_brace = format!("{}{}", prefix, "{");
-            output(diff_op_s, &left_brace);
-            for (key, val) in vmap {
-                format_json_val(
-                    val,
-                    Some(key.to_owned()),
-                    indent_count + INDENT_SIZE,
-                    diff_op,
-                    output,
-                );
+    let mut res = vec![];
+    let mut idx = 0;
+    while idx < lines.len() {
+        let (diff_op, line) = &lines[idx];
+        match diff_op.as_str() {
+            "-" | "+" => {
+                let run_len = lines[idx..]
+                    .iter()
+                    .take_while(|(diff_op, _)| diff_op == "-" || diff_op == "+")
+                    .count();
+                let run = &lines[idx..idx + run_len];
+                let removed: Vec<&String> = run
+                    .iter()
+                    .filter(|(diff_op, _)| diff_op == "-")
+                    .map(|(_, line)| line)
+                    .collect();
+                let added: Vec<&String> = run
+                    .iter()
+                    .filter(|(diff_op, _)| diff_op == "+")
+                    .map(|(_, line)| line)
+                    .collect();
+                for row in 0..removed.len().max(added.len()) {
+                    let (left, right) = (removed.get(row), added.get(row));
+                    let marker = match (left, right) {
+                        (Some(_), Some(_)) => "|",
+                        (Some(_), None) => "<",
+                        _ => ">",
+                    };
+                    res.push(format!(
+                        "{} {} {}",
+                        style_line("-", &text(left.map_or("", |line| line.as_str()))),
+                        marker,
+                        style_line("+", &text(right.map_or("", |line| line.as_str()))),
+                    ));
+                }
+                idx += run_len;
+            }
+            "~" => {
+                // notes about the change span both columns
+                let line = pad_str(line, width, Alignment::Left, Some("…"));
+                res.push(style_line("~", line.trim_end()));
+                idx += 1;
+            }
+            _ => {
+                let text = text(line);
+                res.push(format!("{}   {}", text, text).trim_end().to_owned());
+                idx += 1;
             }
-            let right_brace = format!("{}{}{}", diff_op_s, " ".repeat(indent_count), "}");
-            output(diff_op_s, &right_brace);
         }
-    };
+    }
+    res
 }
 
-fn format_json_loop<F>(
-    jval: &Value,
-    curr_path: &Path,
-    json_diffs: &JsonDiff,
-    indent_count: usize,
-    output: &mut F,
-) where
-    F: FnMut(&str, &str),
-{
-    let key = curr_path.current_key();
-    let indent_key = if let Some(s) = &key {
-        // e.g. for path /a/b/c
-        // ______c:
-        format!(r#"{}{}: "#, " ".repeat(indent_count), s)
-    } else {
-        // for arr index path or empty path, just indent space
-        " ".repeat(indent_count)
-    };
+/// Prints the tree output, in one column or side by side.
+struct TreeOutput {
+    formatter: ConsoleFormatter,
+    side_by_side: bool,
+    width: Option<usize>,
+}
 
-    if let Some(diff_change) = json_diffs.get_diffchange(curr_path) {
-        match diff_change {
-            DiffChange::Remove(val) => format_json_val(val, key, indent_count, Some("-"), output),
-            DiffChange::Replace { old_val, new_val } => {
-                format_json_val(old_val, key.to_owned(), indent_count, Some("-"), output);
-                format_json_val(new_val, key, indent_count, Some("+"), output);
+impl TreeOutput {
+    fn print(&self, left_val: &Value, json_diffs: &JsonDiff) {
+        print!("{}", self.render(left_val, json_diffs));
+    }
+
+    /// The lines of the tree output, each ending with a newline.
+    fn render(&self, left_val: &Value, json_diffs: &JsonDiff) -> String {
+        let mut res = String::new();
+        if self.side_by_side {
+            let mut lines = vec![];
+            let mut output_mut = |diff_opp: &str, line: &str| {
+                lines.push((diff_opp.to_owned(), line.to_owned()));
+            };
+            self.formatter
+                .format_lines(left_val, json_diffs, &mut output_mut);
+            let width = self
+                .width
+                .or_else(|| Term::stdout().size_checked().map(|(_, cols)| cols as usize))
+                .unwrap_or(DEFAULT_WIDTH);
+            for line in format_side_by_side(&lines, width) {
+                res.push_str(&line);
+                res.push('\n');
             }
-            DiffChange::Add(val) => format_json_val(val, key, indent_count, Some("+"), output),
+        } else {
+            let mut output_mut = |diff_opp: &str, line: &str| {
+                res.push_str(&style_line(diff_opp, line));
+                res.push('\n');
+            };
+            self.formatter
+                .format_lines(left_val, json_diffs, &mut output_mut);
         }
-    } else {
-        match jval {
-            Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => {
-                format_json_val(jval, key, indent_count, None, output)
-            }
-            Value::Object(vmap) => {
-                let left_brace = format!("{}{}{}", " ", indent_key, "{");
-                output(" ", &left_brace);
-
-                {
-                    // added keys
-                    let mut path = curr_path.clone();
-                    if let Some(add_keys) = json_diffs.get_add_keys(&path) {
-                        for key in add_keys {
-                            path.push_key(key);
-                            let val = json_diffs.get_diffchange(&path).unwrap();
-                            assert!(matches!(val, DiffChange::Add(_)));
-                            if let DiffChange::Add(new_val) = val {
-                                format_json_val(
-                                    new_val,
-                                    Some(key.to_string()),
-                                    indent_count + INDENT_SIZE,
-                                    Some("+"),
-                                    output,
-                                );
-                            }
-                            path.pop();
-                        }
-                    }
-                }
+        res
+    }
+}
 
-                for (key, val) in vmap {
-                    let new_path = curr_path.clone_then_add_key(key);
-                    format_json_loop(
-                        val,
-                        &new_path,
-                        json_diffs,
-                        indent_count + INDENT_SIZE,
-                        output,
-                    );
+/// Print every line of `doc` as added (`+`) or removed (`-`), for a file that only one side has.
+fn print_whole(doc: &Value, diff_op: &str) {
+    let mut output_mut = |_: &str, line: &str| {
+        let line = format!("{}{}", diff_op, line.get(1..).unwrap_or_default());
+        println!("{}", style_line(diff_op, &line));
+    };
+    ConsoleFormatter::default().format_lines(doc, &JsonDiff::diff(doc, doc), &mut output_mut);
+}
+
+/// Diff the json files of two directories that pass `filter`, paired by their relative path.
+/// Prints a line for every added, removed and changed file when `summary` is set, and then
+/// the tree output of the changed files with `tree`.
+fn diff_dirs(
+    left_dir: &str,
+    right_dir: &str,
+    filter: &dir::FileFilter,
+    opts: &DiffOptions,
+    json_ptr: &JsonPointer,
+    tree: Option<&TreeOutput>,
+    summary: bool,
+) -> Result<ExitCode> {
+    let pairs = dir::pair_files(Path::new(left_dir), Path::new(right_dir), filter)?;
+    let read = |path: &Path| -> Result<Value> {
+        Ok(serde_json::from_str(&read_json_file(
+            &path.display().to_string(),
+        )?)?)
+    };
+    let mut changed = vec![];
+    let (mut added, mut removed, mut unchanged) = (0, 0, 0);
+    let mut summary_lines = vec![];
+    for pair in pairs.iter() {
+        match (&pair.left, &pair.right) {
+            (Some(left), Some(right)) => {
+                let v = read(left)?;
+                let v2 = read(right)?;
+                let json_diffs = JsonDiff::diff_at(&v, &v2, json_ptr, opts, PathMode::Relative)
+                    .with_context(|| pair.rel_path.clone())?;
+                let changes = json_diffs.diffs().len();
+                if changes == 0 {
+                    unchanged += 1;
+                    continue;
                 }
-                let right_brace = format!("{}{}{}", " ", " ".repeat(indent_count), "}");
-                output(" ", &right_brace)
+                let plural = if changes == 1 { "" } else { "s" };
+                summary_lines.push(style_line(
+                    "~",
+                    &format!("~ {}: {} change{}", pair.rel_path, changes, plural),
+                ));
+                changed.push((left, right, v, json_diffs));
             }
-            Value::Array(arr) => {
-                let left_bracket = format!("{}{}{}", " ", indent_key, "[");
-                output(" ", &left_bracket);
-
-                let empty_vec = &vec![];
-                let arr_changes = json_diffs.get_arr_changes(curr_path).unwrap_or(&empty_vec);
-                let mut old_idx: usize = 0;
-                let mut new_idx: usize = 0;
-                let mut new_len = arr.len();
-                let mut diffchange_idx: usize = 0;
-                while new_idx < new_len
-                    || (new_idx >= new_len && diffchange_idx < arr_changes.len())
-                // when adding to the end of the arr
-                {
-                    if diffchange_idx < arr_changes.len() {
-                        let diff_change = &arr_changes[diffchange_idx];
-                        let idx = diff_change.path.arr_idx().unwrap();
-                        if idx == new_idx {
-                            match &diff_change.diff {
-                                DiffChange::Replace { old_val, new_val } => {
-                                    format_json_val(
-                                        old_val,
-                                        None,
-                                        indent_count + INDENT_SIZE,
-                                        Some("-"),
-                                        output,
-                                    );
-                                    format_json_val(
-                                        new_val,
-                                        None,
-                                        indent_count + INDENT_SIZE,
-                                        Some("+"),
-                                        output,
-                                    );
-                                    diffchange_idx += 1;
-                                    old_idx += 1;
-                                    new_idx += 1;
-                                }
-                                DiffChange::Add(val) => {
-                                    format_json_val(
-                                        val,
-                                        None,
-                                        indent_count + INDENT_SIZE,
-                                        Some("+"),
-                                        output,
-                                    );
-                                    new_idx += 1;
-                                    new_len += 1;
-                                    diffchange_idx += 1;
-                                }
-                                DiffChange::Remove(_) => {
-                                    let mut curr_remove_idx =
-                                        arr_changes[diffchange_idx].path.arr_idx().unwrap();
-                                    while diffchange_idx < arr_changes.len()
-                                        && arr_changes[diffchange_idx].diff.is_remove()
-                                        && arr_changes[diffchange_idx].path.arr_idx().unwrap()
-                                            == curr_remove_idx
-                                    {
-                                        let diff_change = &arr_changes[diffchange_idx];
-                                        let remove_val = diff_change.diff.as_remove().unwrap();
-
-                                        format_json_val(
-                                            remove_val,
-                                            None,
-                                            indent_count + INDENT_SIZE,
-                                            Some("-"),
-                                            output,
-                                        );
-
-                                        assert_eq!(remove_val, &arr[old_idx]);
-                                        old_idx += 1;
-                                        new_len -= 1;
-                                        diffchange_idx += 1;
-                                        curr_remove_idx += 1;
-                                    }
-                                }
-                            }
-                        } else {
-                            let path = curr_path.clone_then_add_idx(old_idx);
-                            format_json_loop(
-                                &arr[old_idx],
-                                &path,
-                                json_diffs,
-                                indent_count + INDENT_SIZE,
-                                output,
-                            );
-                            old_idx += 1;
-                            new_idx += 1;
-                        }
-                    } else {
-                        let path = curr_path.clone_then_add_idx(old_idx);
-                        format_json_loop(
-                            &arr[old_idx],
-                            &path,
-                            json_diffs,
-                            indent_count + INDENT_SIZE,
-                            output,
-                        );
-                        old_idx += 1;
-                        new_idx += 1;
-                    }
-                }
-
-                let right_bracket = format!("{}{}", " ".repeat(indent_count), "]");
-                output(" ", &right_bracket);
+            (Some(_), None) => {
+                removed += 1;
+                summary_lines.push(style_line("-", &format!("- {}: removed", pair.rel_path)));
+            }
+            (None, Some(_)) => {
+                added += 1;
+                summary_lines.push(style_line("+", &format!("+ {}: added", pair.rel_path)));
             }
+            (None, None) => unreachable!("a file of neither directory"),
         }
     }
-}
 
-fn read_json_file(path: &str) -> String {
-    let res = fs::read_to_string(path);
-    match res {
-        Ok(content) => {
-            let json: serde_json::Result<Value> = serde_json::from_str(&content);
-            if let Err(err) = json {
-                println!("Json `{}` parse error: {}", path, err);
-                process::exit(1);
-            }
-            content
+    if summary {
+        for line in summary_lines.iter() {
+            println!("{}", line);
         }
-        Err(err) => {
-            println!("{}: {}", path, err);
-            process::exit(1);
+        println!(
+            "{} changed, {} added, {} removed and {} unchanged files",
+            changed.len(),
+            added,
+            removed,
+            unchanged
+        );
+    }
+    if let Some(tree) = tree {
+        let bold = Style::new().bold();
+        for (left, right, v, json_diffs) in changed.iter() {
+            println!();
+            println!("{}", bold.apply_to(format!("--- {}", left.display())));
+            println!("{}", bold.apply_to(format!("+++ {}", right.display())));
+            tree.print(json_ptr.get(v)?, json_diffs);
         }
     }
+    Ok(diff_status(!changed.is_empty() || added + removed > 0))
+}
+
+/// Exit status when the documents differ, as diff(1) sets it.
+const EXIT_DIFFERENT: u8 = 1;
+/// Exit status on errors.
+const EXIT_ERROR: u8 = 2;
+
+/// Read a json file, `-` reads stdin.
+fn read_json_file(path: &str) -> Result<String> {
+    let content = if path == "-" {
+        let mut content = String::new();
+        io::stdin()
+            .read_to_string(&mut content)
+            .context("can not read stdin")?;
+        content
+    } else {
+        fs::read_to_string(path).with_context(|| path.to_owned())?
+    };
+    serde_json::from_str::<Value>(&content)
+        .with_context(|| format!("Json `{}` parse error", path))?;
+    Ok(content)
+}
+
+fn diff_status(changed: bool) -> ExitCode {
+    if changed {
+        ExitCode::from(EXIT_DIFFERENT)
+    } else {
+        ExitCode::SUCCESS
+    }
 }
 
 #[derive(Parser)]
@@ -263,14 +260,323 @@ struct Cli {
     command: Commands,
 }
 
+#[derive(ValueEnum, Clone, Debug)]
+enum OutputFormat {
+    /// the left json with the changes interleaved
+    Tree,
+    /// a json patch (RFC 6902) that turns the left json into the right json
+    JsonPatch,
+    /// a self-contained html page
+    Html,
+    /// a markdown table of the changes
+    Markdown,
+    /// a table of two arrays of records paired by --record-key
+    Table,
+    /// the table as csv
+    Csv,
+    /// a Graphviz graph of the changed tree
+    Dot,
+    /// file:line:col: message for every change
+    Locations,
+    /// GitHub Actions warning annotations
+    Github,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+enum Engine {
+    /// compare objects key by key and arrays element by element
+    Structural,
+    /// follow a minimal cost tree edit (Zhang–Shasha)
+    TreeEdit,
+}
+
+/// How to compare two json documents.
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// report reordered object keys (needs the `preserve_order` feature)
+    #[clap(long)]
+    key_order: bool,
+
+    /// treat a missing key and a key set to null as equal
+    #[clap(long)]
+    null_as_missing: bool,
+
+    /// treat a missing key and a key set to its value in this json file as equal
+    #[clap(long, value_name = "DEFAULTS_JSON")]
+    defaults: Option<String>,
+
+    /// compare values after normalizing them, one of trim, casefold, nfc, timestamp,
+    /// sort-arrays, lowercase-keys, optionally only at a json pointer (e.g. sort-arrays=/tags)
+    #[clap(long, value_name = "NAME[=JSON_POINTER]", multiple_occurrences = true)]
+    normalize: Vec<String>,
+
+    /// report a removed key and an added key with similar values as a rename, SIMILARITY
+    /// is between 0 and 1, the default 1 only pairs equal values
+    #[clap(
+        long,
+        value_name = "SIMILARITY",
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "1",
+        value_parser = parse_similarity
+    )]
+    detect_renames: Option<f64>,
+
+    /// align array elements that are similar, not only equal ones, so that the changes
+    /// inside them are shown, SIMILARITY is between 0 and 1 (default 0.5)
+    #[clap(
+        long,
+        value_name = "SIMILARITY",
+        min_values = 0,
+        max_values = 1,
+        require_equals = true,
+        default_missing_value = "0.5",
+        value_parser = parse_similarity
+    )]
+    fuzzy_arrays: Option<f64>,
+
+    /// report an object or array that moved to another key path as a move
+    #[clap(long)]
+    detect_moves: bool,
+
+    /// report an added object or array that equals an unchanged one as a copy
+    #[clap(long)]
+    detect_copies: bool,
+
+    /// how to find the changes
+    #[clap(long, value_enum, default_value = "structural")]
+    engine: Engine,
+
+    /// summarize the changes of objects and arrays at this depth, e.g. 1 for the values of
+    /// the top level keys
+    #[clap(long, value_name = "DEPTH")]
+    max_depth: Option<usize>,
+
+    /// costs of inserting, deleting and renaming a node for the tree-edit engine
+    #[clap(long, value_name = "INSERT,DELETE,RENAME", default_value = "1,1,1")]
+    edit_costs: String,
+}
+
+/// Fail when one of `flags`, the name of a flag and whether it was given, was given.
+fn reject_given(flags: &[(&str, bool)], used_with: &str) -> Result<()> {
+    match flags.iter().find(|(_, given)| *given) {
+        Some((flag, _)) => bail!("{} can not be used with {}", flag, used_with),
+        None => Ok(()),
+    }
+}
+
+impl DiffArgs {
+    /// The flags that only the structural engine applies and whether they were given.
+    fn structural_flags(&self) -> Vec<(&'static str, bool)> {
+        vec![
+            ("--key-order", self.key_order),
+            ("--null-as-missing", self.null_as_missing),
+            ("--defaults", self.defaults.is_some()),
+            ("--normalize", !self.normalize.is_empty()),
+            ("--detect-renames", self.detect_renames.is_some()),
+            ("--fuzzy-arrays", self.fuzzy_arrays.is_some()),
+            ("--max-depth", self.max_depth.is_some()),
+        ]
+    }
+
+    /// Fail when a flag is given that the table and csv formats ignore, they compare the
+    /// fields of the records as they are.
+    fn check_records(&self) -> Result<()> {
+        let mut flags = self.structural_flags();
+        flags.extend([
+            ("--detect-moves", self.detect_moves),
+            ("--detect-copies", self.detect_copies),
+            ("--engine", !matches!(self.engine, Engine::Structural)),
+        ]);
+        reject_given(&flags, "--format table or csv")
+    }
+
+    fn to_options(&self) -> Result<DiffOptions> {
+        if self.key_order && !cfg!(feature = "preserve_order") {
+            bail!("--key-order needs the key order of the files, build with the `preserve_order` feature");
+        }
+        if let Engine::TreeEdit = self.engine {
+            // the tree edit compares the values as they are
+            reject_given(&self.structural_flags(), "--engine tree-edit")?;
+        }
+        let defaults = match &self.defaults {
+            // the files to diff may be read from stdin too
+            Some(path) if path == "-" => bail!("--defaults can not be read from stdin"),
+            Some(path) => Some(serde_json::from_str(&read_json_file(path)?)?),
+            None => None,
+        };
+        let normalizers = self
+            .normalize
+            .iter()
+            .map(|spec| normalizer::from_spec(spec))
+            .collect::<Result<Vec<_>>>()?;
+        let arr_diff = match self.fuzzy_arrays {
+            Some(threshold) => ArrDiffMethod::Fuzzy { threshold },
+            None => ArrDiffMethod::Lcs,
+        };
+        let engine = match self.engine {
+            Engine::Structural => DiffEngine::Structural,
+            Engine::TreeEdit => DiffEngine::TreeEdit(self.edit_costs.parse()?),
+        };
+        Ok(DiffOptions {
+            engine,
+            arr_diff,
+            key_order: self.key_order,
+            null_as_missing: self.null_as_missing,
+            defaults,
+            normalizers,
+            rename_threshold: self.detect_renames,
+            detect_moves: self.detect_moves,
+            detect_copies: self.detect_copies,
+            max_depth: self.max_depth,
+            ..Default::default()
+        })
+    }
+}
+
+/// A similarity threshold of the command line, between 0 and 1.
+fn parse_similarity(s: &str) -> Result<f64, String> {
+    let similarity: f64 = s.parse().map_err(|_| format!("`{}` is not a number", s))?;
+    if (0.0..=1.0).contains(&similarity) {
+        Ok(similarity)
+    } else {
+        Err(format!("{} is not between 0 and 1", similarity))
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// diff two json file
     Diff {
         left_json: String,
         right_json: String,
+
+        #[clap(flatten)]
+        diff_args: DiffArgs,
+
+        /// only diff the values at this json pointer (e.g. /spec/template)
+        #[clap(long, value_name = "JSON_POINTER")]
+        path: Option<String>,
+
+        /// report the paths of a --path diff from the root of the documents, not from the
+        /// values at the json pointer
+        #[clap(long, requires = "path", conflicts_with = "recursive")]
+        absolute_paths: bool,
+
+        /// only show the changes and N unchanged keys or items around them
+        #[clap(long, value_name = "N")]
+        context: Option<usize>,
+
+        /// show the left and the right json in two columns
+        #[clap(long)]
+        side_by_side: bool,
+
+        /// width of the side by side output, the width of the terminal by default
+        #[clap(long, value_name = "COLUMNS", requires = "side-by-side")]
+        width: Option<usize>,
+
+        /// fold the unchanged objects and arrays of the html output
+        #[clap(long)]
+        collapse_unchanged: bool,
+
+        /// cut off values in the markdown table after this many characters
+        #[clap(long, value_name = "CHARS", default_value = "80")]
+        max_value_len: usize,
+
+        /// show at most N changes in the markdown table
+        #[clap(long, value_name = "N")]
+        max_rows: Option<usize>,
+
+        /// add the tree output to the markdown in a diff block
+        #[clap(long)]
+        diff_block: bool,
+
+        /// json pointer to the key of the records for the table and csv formats (e.g. /id)
+        #[clap(long, value_name = "JSON_POINTER")]
+        record_key: Option<String>,
+
+        /// print nothing, only exit with 1 when the files differ and 0 when they are the same
+        #[clap(short, long, conflicts_with = "check")]
+        quiet: bool,
+
+        /// only print whether the files differ and the number of changes
+        #[clap(long)]
+        check: bool,
+
+        /// compare the json files of two directories, paired by their relative path
+        #[clap(short, long)]
+        recursive: bool,
+
+        /// only compare the files whose relative path matches one of these globs, `*.json` by
+        /// default
+        #[clap(
+            long,
+            value_name = "GLOB",
+            multiple_occurrences = true,
+            requires = "recursive"
+        )]
+        include: Vec<String>,
+
+        /// do not compare the files whose relative path matches one of these globs
+        #[clap(
+            long,
+            value_name = "GLOB",
+            multiple_occurrences = true,
+            requires = "recursive"
+        )]
+        exclude: Vec<String>,
+
+        /// diff again and redraw the terminal whenever one of the files changes on disk
+        #[clap(long, conflicts_with_all = &["recursive", "quiet", "check"])]
+        watch: bool,
+
+        /// with --watch, only show how the files changed since the last time they were shown
+        #[clap(long, requires = "watch")]
+        since_last: bool,
+
+        /// output format
+        #[clap(long, value_enum, default_value = "tree")]
+        format: OutputFormat,
     },
 
+    /// print how similar the right json files are to the left json, between 0 and 1 (equal),
+    /// the most similar first
+    Similarity {
+        left_json: String,
+        #[clap(required = true)]
+        right_jsons: Vec<String>,
+
+        #[clap(flatten)]
+        diff_args: DiffArgs,
+    },
+
+    /// diff a file for git, with the seven arguments git calls GIT_EXTERNAL_DIFF and the
+    /// command of a diff driver with
+    GitDiff {
+        path: String,
+        old_file: String,
+        old_hex: String,
+        old_mode: String,
+        new_file: String,
+        new_hex: String,
+        new_mode: String,
+        /// the new path of a renamed or copied file
+        new_path: Option<String>,
+        /// how git describes the rename or copy, e.g. its similarity index
+        rename_msg: Option<String>,
+
+        #[clap(flatten)]
+        diff_args: DiffArgs,
+
+        /// show N unchanged keys or items around the changes
+        #[clap(long, value_name = "N", default_value = "3")]
+        context: usize,
+    },
+
+    /// print a json file with sorted keys and indented, for the textconv of git
+    Textconv { json_file: String },
+
     /// patch a json object with a patch document
     Patch {
         original_json: String,
@@ -278,41 +584,323 @@ enum Commands {
     },
 }
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
+    match run() {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("error: {:#}", err);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn run() -> Result<ExitCode> {
     let args = Cli::parse();
     match args.command {
         Commands::Diff {
             left_json,
             right_json,
+            diff_args,
+            path,
+            absolute_paths,
+            context,
+            side_by_side,
+            width,
+            collapse_unchanged,
+            max_value_len,
+            max_rows,
+            diff_block,
+            record_key,
+            quiet,
+            check,
+            recursive,
+            include,
+            exclude,
+            watch,
+            since_last,
+            format,
         } => {
-            let json1 = read_json_file(&left_json);
-            let json2 = read_json_file(&right_json);
-
-            let json_diffs = JsonDiff::diff_json(&json1, &json2)?;
-
-            let mut output_mut = |diff_opp: &str, line: &str| {
-                let str_output = match diff_opp {
-                    "+" => format!("{}", Style::new().green().apply_to(line)),
-                    "-" => format!("{}", Style::new().red().apply_to(line)),
-                    _ => line.to_owned(),
+            if side_by_side && !matches!(format, OutputFormat::Tree) {
+                bail!("--side-by-side only applies to the tree format");
+            }
+            if context.is_some() && !matches!(format, OutputFormat::Tree | OutputFormat::Markdown) {
+                bail!("--context only applies to the tree and markdown formats");
+            }
+            let tree = TreeOutput {
+                formatter: ConsoleFormatter { context },
+                side_by_side,
+                width,
+            };
+            if recursive {
+                if !matches!(format, OutputFormat::Tree) {
+                    bail!("--recursive only prints the tree format");
+                }
+                return diff_dirs(
+                    &left_json,
+                    &right_json,
+                    &dir::FileFilter::new(&include, &exclude)?,
+                    &diff_args.to_options()?,
+                    &JsonPointer::new(path.as_deref().unwrap_or_default())?,
+                    (!quiet && !check).then_some(&tree),
+                    !quiet,
+                );
+            }
+            if watch {
+                if !matches!(format, OutputFormat::Tree) {
+                    bail!("--watch only prints the tree format");
+                }
+                if left_json == "-" || right_json == "-" {
+                    bail!("--watch can not read stdin");
+                }
+                let opts = diff_args.to_options()?;
+                let json_ptr = JsonPointer::new(path.as_deref().unwrap_or_default())?;
+                let diff = |v: &Value, v2: &Value| -> Result<String> {
+                    let json_diffs =
+                        JsonDiff::diff_at(v, v2, &json_ptr, &opts, PathMode::Relative)?;
+                    Ok(tree.render(json_ptr.get(v)?, &json_diffs))
+                };
+                let load = |file: &str| -> Result<Value> {
+                    Ok(serde_json::from_str(&read_json_file(file)?)?)
                 };
-                println!("{}", str_output);
+                // the documents of the last render
+                let mut last: Option<(Value, Value)> = None;
+                watch::watch(&[&left_json, &right_json], || {
+                    let files = [left_json.as_str(), right_json.as_str()];
+                    let output = watch::reload(files, load, diff, since_last, &mut last)?;
+                    Term::stdout().clear_screen()?;
+                    print!("{}", output);
+                    Ok(())
+                })?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            if left_json == "-" && right_json == "-" {
+                bail!("only one of the json files can be read from stdin");
+            }
+            let json1 = read_json_file(&left_json)?;
+            let json2 = read_json_file(&right_json)?;
+            // with --quiet or --check only the exit status and a summary are reported
+            let report_status = |changed: bool, changes: usize| {
+                if check && changed {
+                    let plural = if changes == 1 { "" } else { "s" };
+                    println!(
+                        "{} and {} differ: {} change{}",
+                        left_json, right_json, changes, plural
+                    );
+                }
+                diff_status(changed)
             };
-
+            let opts = diff_args.to_options()?;
             let v: Value = serde_json::from_str(&json1)?;
+            let v2: Value = serde_json::from_str(&json2)?;
+            let json_ptr = JsonPointer::new(path.as_deref().unwrap_or_default())?;
+            // locations are looked up from the root of the files
+            let located = matches!(format, OutputFormat::Locations | OutputFormat::Github);
+            let path_mode = if absolute_paths || located {
+                PathMode::Absolute
+            } else {
+                PathMode::Relative
+            };
+            if let OutputFormat::Table | OutputFormat::Csv = format {
+                diff_args.check_records()?;
+                let record_key = record_key.context(
+                    "the table and csv formats need the key of the records (--record-key)",
+                )?;
+                let table = RecordTable::compare(
+                    json_ptr.get(&v)?,
+                    json_ptr.get(&v2)?,
+                    &JsonPointer::new(&record_key)?,
+                )?;
+                let changed = !table.is_unchanged();
+                if quiet || check {
+                    return Ok(report_status(changed, table.changed_records()));
+                }
+                match format {
+                    OutputFormat::Csv => print!("{}", table.to_csv()),
+                    _ => print!("{}", table.format_text(style_line)),
+                }
+                return Ok(diff_status(changed));
+            }
+            let json_diffs = JsonDiff::diff_at(&v, &v2, &json_ptr, &opts, path_mode)?;
+            let changes = json_diffs.diffs().len();
+            let status = diff_status(changes > 0);
+            if quiet || check {
+                return Ok(report_status(changes > 0, changes));
+            }
 
-            format_json_loop(&v, &Path::empty(), &json_diffs, 1, &mut output_mut);
+            if let OutputFormat::JsonPatch = format {
+                println!("{}", serde_json::to_string_pretty(&json_diffs.to_patch())?);
+                return Ok(status);
+            }
+
+            // the left document the paths of the changes start from
+            let left_val = match path_mode {
+                PathMode::Relative => json_ptr.get(&v)?,
+                PathMode::Absolute => &v,
+            };
+            let report: Option<Box<dyn DiffFormatter>> = match format {
+                OutputFormat::Html => Some(Box::new(HtmlFormatter { collapse_unchanged })),
+                OutputFormat::Markdown => Some(Box::new(MarkdownFormatter {
+                    max_value_len,
+                    max_rows,
+                    diff_block,
+                    context,
+                })),
+                OutputFormat::Dot => Some(Box::new(DotFormatter)),
+                OutputFormat::Locations | OutputFormat::Github => {
+                    let style = match format {
+                        OutputFormat::Github => LocationStyle::GitHub,
+                        _ => LocationStyle::Plain,
+                    };
+                    Some(Box::new(LocationFormatter::new(
+                        &left_json,
+                        &json1,
+                        &right_json,
+                        &json2,
+                        style,
+                    )?))
+                }
+                OutputFormat::Tree
+                | OutputFormat::JsonPatch
+                | OutputFormat::Table
+                | OutputFormat::Csv => None,
+            };
+            if let Some(formatter) = report {
+                print!("{}", formatter.format(left_val, &json_diffs));
+                return Ok(status);
+            }
+            tree.print(left_val, &json_diffs);
+            return Ok(status);
+        }
+        Commands::Similarity {
+            left_json,
+            right_jsons,
+            diff_args,
+        } => {
+            let opts = diff_args.to_options()?;
+            let v: Value = serde_json::from_str(&read_json_file(&left_json)?)?;
+            let mut scores = vec![];
+            for right_json in right_jsons.iter() {
+                let v2: Value = serde_json::from_str(&read_json_file(right_json)?)?;
+                scores.push((
+                    similarity::similarity_with_options(&v, &v2, &opts),
+                    right_json,
+                ));
+            }
+            if let [(score, _)] = scores[..] {
+                println!("{:.4}", score);
+            } else {
+                scores.sort_by(|(s0, _), (s1, _)| s1.total_cmp(s0));
+                for (score, right_json) in scores {
+                    println!("{:.4}\t{}", score, right_json);
+                }
+            }
+        }
+        Commands::GitDiff {
+            path,
+            old_file,
+            old_hex: _,
+            old_mode,
+            new_file,
+            new_hex: _,
+            new_mode,
+            new_path,
+            rename_msg,
+            diff_args,
+            context,
+        } => {
+            let opts = diff_args.to_options()?;
+            // git passes the missing side of an added or deleted file as /dev/null
+            let read = |file: &str| -> Result<Option<Value>> {
+                if git::is_null_file(file) {
+                    return Ok(None);
+                }
+                let content = read_json_file(file).with_context(|| path.clone())?;
+                Ok(Some(serde_json::from_str(&content)?))
+            };
+            let (v, v2) = (read(&old_file)?, read(&new_file)?);
+            let bold = Style::new().bold();
+            let header = git::header(
+                &path,
+                new_path.as_deref().unwrap_or(&path),
+                &old_file,
+                &old_mode,
+                &new_file,
+                &new_mode,
+                rename_msg.as_deref(),
+            );
+            for line in header {
+                println!("{}", bold.apply_to(line));
+            }
+            match (v, v2) {
+                (Some(v), Some(v2)) => {
+                    let json_diffs = JsonDiff::diff_with_options(&v, &v2, &opts);
+                    if !json_diffs.diffs().is_empty() {
+                        let tree = TreeOutput {
+                            formatter: ConsoleFormatter {
+                                context: Some(context),
+                            },
+                            side_by_side: false,
+                            width: None,
+                        };
+                        tree.print(&v, &json_diffs);
+                    }
+                }
+                (None, Some(doc)) => print_whole(&doc, "+"),
+                (Some(doc), None) => print_whole(&doc, "-"),
+                (None, None) => {}
+            }
+            // git stops at the first file an external diff fails on, so a difference is no error
+        }
+        Commands::Textconv { json_file } => {
+            let v: Value = serde_json::from_str(&read_json_file(&json_file)?)?;
+            println!("{}", serde_json::to_string_pretty(&git::canonical(v))?);
         }
         Commands::Patch {
             original_json,
             patch_json,
         } => {
-            let patch_str = read_json_file(&patch_json);
+            let patch_str = read_json_file(&patch_json)?;
             let jp: JsonPatch = JsonPatch::try_from(patch_str)?;
-            let original_json_str = read_json_file(&original_json);
+            let original_json_str = read_json_file(&original_json)?;
             let res = jp.apply(&serde_json::from_str(&original_json_str)?)?;
             println!("{}", serde_json::to_string_pretty(&res)?);
         }
     }
-    Ok(())
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::format_side_by_side;
+
+    #[test]
+    fn test_format_side_by_side() {
+        console::set_colors_enabled(false);
+        let lines: Vec<(String, String)> = [
+            (" ", "  {"),
+            ("-", "-     a: 1"),
+            ("+", "+     a: 2"),
+            ("+", "+     b: 3"),
+            ("~", "~     (renamed) c -> d"),
+            ("-", "-     e: \"a long value\""),
+            (" ", "  }"),
+        ]
+        .iter()
+        .map(|(diff_op, line)| (diff_op.to_string(), line.to_string()))
+        .collect();
+        // a removed line faces the line added in its place, long lines are cut off
+        assert_eq!(
+            format_side_by_side(&lines, 31),
+            [
+                " {                {",
+                "     a: 1      |      a: 2     ",
+                "               >      b: 3     ",
+                "~     (renamed) c -> d",
+                "     e: \"a lo… <               ",
+                " }                }",
+            ]
+        );
+        // notes span both columns and are cut off at the width
+        assert_eq!(format_side_by_side(&lines[4..5], 10), ["~     (re…"]);
+    }
 }
@@ -0,0 +1,164 @@
+//! Render again whenever one of some files changes on disk.
+
+use anyhow::{Context, Result};
+use console::Style;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde_json::Value;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long the files must stay untouched before they are rendered again, so a file that is
+/// written in several steps is rendered once.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Call `render` now and after every change of `files`, until the process is stopped. An error
+/// of `render`, e.g. a file that is not valid json while it is written, is printed and the
+/// files are watched on.
+pub fn watch<F>(files: &[&str], mut render: F) -> Result<()>
+where
+    F: FnMut() -> Result<()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mut paths: Vec<PathBuf> = vec![];
+    for file in files {
+        // editors often replace a file instead of writing it, which ends a watch of the file,
+        // so its directory is watched
+        let path = Path::new(file);
+        let name = path
+            .file_name()
+            .with_context(|| format!("{} is not a file", file))?;
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let dir = dir
+            .canonicalize()
+            .with_context(|| format!("{}: can not watch the directory", file))?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+        paths.push(dir.join(name));
+    }
+    let is_change = |event: &Event| {
+        !matches!(event.kind, EventKind::Access(_))
+            && event.paths.iter().any(|path| paths.contains(path))
+    };
+
+    loop {
+        if let Err(err) = render() {
+            eprintln!("error: {:#}", err);
+        }
+        loop {
+            if is_change(&rx.recv()??) {
+                break;
+            }
+        }
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+    }
+}
+
+/// Load the documents of the two `files` with `load` and return what a render shows: a header
+/// and the output of `diff` for the documents. With `since_last` a render after the first one
+/// instead shows how every changed file changed since the last render.
+///
+/// `last` keeps the documents of the last render. It is only replaced when the render
+/// succeeds, so the next render after a failed one still compares with the last shown files.
+pub fn reload<L, D>(
+    files: [&str; 2],
+    load: L,
+    mut diff: D,
+    since_last: bool,
+    last: &mut Option<(Value, Value)>,
+) -> Result<String>
+where
+    L: Fn(&str) -> Result<Value>,
+    D: FnMut(&Value, &Value) -> Result<String>,
+{
+    let docs = (load(files[0])?, load(files[1])?);
+    let bold = Style::new().bold();
+    let mut output = String::new();
+    match last.as_ref().filter(|_| since_last) {
+        Some((last_v, last_v2)) => {
+            let changed: Vec<_> = [(files[0], last_v, &docs.0), (files[1], last_v2, &docs.1)]
+                .into_iter()
+                .filter(|(_, last_val, val)| last_val != val)
+                .collect();
+            if changed.is_empty() {
+                let header = "no changes since the last render";
+                writeln!(output, "{}", bold.apply_to(header))?;
+            }
+            for (file, last_val, val) in changed {
+                let header = format!("{} since the last render", file);
+                writeln!(output, "{}", bold.apply_to(header))?;
+                output.push_str(&diff(last_val, val)?);
+            }
+        }
+        None => {
+            let header = format!("{} → {}", files[0], files[1]);
+            writeln!(output, "{}", bold.apply_to(header))?;
+            output.push_str(&diff(&docs.0, &docs.1)?);
+        }
+    }
+    *last = Some(docs);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watch::reload;
+    use anyhow::{anyhow, Result};
+    use serde_json::{json, Value};
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_reload() {
+        console::set_colors_enabled(false);
+        let files = RefCell::new(HashMap::from([("l", json!(1)), ("r", json!(2))]));
+        let load = |file: &str| -> Result<Value> {
+            match &files.borrow()[file] {
+                Value::Null => Err(anyhow!("{} is not valid json", file)),
+                val => Ok(val.clone()),
+            }
+        };
+        // a diff of a string fails, e.g. when the --path is gone
+        let diff = |v: &Value, v2: &Value| -> Result<String> {
+            match v2 {
+                Value::String(_) => Err(anyhow!("can not diff")),
+                _ => Ok(format!("{} -> {}\n", v, v2)),
+            }
+        };
+        let set = |file: &'static str, val: Value| {
+            files.borrow_mut().insert(file, val);
+        };
+
+        let mut last = None;
+        let render = reload(["l", "r"], load, diff, true, &mut last).unwrap();
+        assert_eq!(render, "l → r\n1 -> 2\n");
+        let render = reload(["l", "r"], load, diff, true, &mut last).unwrap();
+        assert_eq!(render, "no changes since the last render\n");
+
+        set("r", json!(3));
+        let render = reload(["l", "r"], load, diff, true, &mut last).unwrap();
+        assert_eq!(render, "r since the last render\n2 -> 3\n");
+
+        // failed renders keep the documents of the last render
+        set("r", Value::Null);
+        assert!(reload(["l", "r"], load, diff, true, &mut last).is_err());
+        set("r", json!("x"));
+        assert!(reload(["l", "r"], load, diff, true, &mut last).is_err());
+        assert_eq!(last, Some((json!(1), json!(3))));
+        set("r", json!(4));
+        set("l", json!(0));
+        let render = reload(["l", "r"], load, diff, true, &mut last).unwrap();
+        assert_eq!(
+            render,
+            "l since the last render\n1 -> 0\nr since the last render\n3 -> 4\n"
+        );
+
+        // without since_last every render diffs the files
+        let render = reload(["l", "r"], load, diff, false, &mut last).unwrap();
+        assert_eq!(render, "l → r\n0 -> 4\n");
+    }
+}
@@ -0,0 +1,455 @@
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Run the cli in `tests/fixtures` with `args`, writing `stdin` to its standard input.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_jsondiffpath-cli"))
+        .current_dir(fixtures_dir())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("the cli runs");
+    // the cli may exit without reading stdin, which closes the pipe
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn fixture(name: &str) -> String {
+    std::fs::read_to_string(fixtures_dir().join(name)).unwrap()
+}
+
+#[test]
+fn same_files_exit_0() {
+    let output = run(&["diff", "left.json", "left.json"], "");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn different_files_exit_1() {
+    let output = run(&["diff", "left.json", "right.json"], "");
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(stdout(&output).contains("gadget"));
+}
+
+#[test]
+fn errors_exit_2_on_stderr() {
+    let cases: [&[&str]; 3] = [
+        &["diff", "left.json", "invalid.json"],
+        &["diff", "left.json", "missing.json"],
+        &["diff", "--detect-renames=2", "left.json", "right.json"],
+    ];
+    for args in cases {
+        let output = run(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stdout(&output).is_empty(), "{:?}", args);
+        assert!(stderr(&output).starts_with("error"), "{:?}", args);
+    }
+}
+
+#[test]
+fn quiet_prints_nothing() {
+    let output = run(&["diff", "--quiet", "left.json", "right.json"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).is_empty());
+    let output = run(&["diff", "-q", "left.json", "left.json"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn check_prints_the_number_of_changes() {
+    let output = run(&["diff", "--check", "left.json", "right.json"], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stdout(&output),
+        "left.json and right.json differ: 2 changes\n"
+    );
+    let output = run(&["diff", "--check", "left.json", "left.json"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).is_empty());
+}
+
+#[test]
+fn reads_stdin() {
+    let output = run(&["diff", "-", "right.json"], &fixture("left.json"));
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(stdout(&output).contains("gadget"));
+    let output = run(&["diff", "left.json", "-"], &fixture("left.json"));
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+}
+
+#[test]
+fn stdin_only_once() {
+    let output = run(&["diff", "-", "-"], &fixture("left.json"));
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("stdin"));
+    let args = ["diff", "--defaults", "-", "-", "right.json"];
+    let output = run(&args, &fixture("left.json"));
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("--defaults can not be read from stdin"));
+}
+
+#[test]
+fn git_diff_of_a_renamed_file() {
+    // git passes the new path and the rename message after the 7 arguments of a change
+    let output = run(
+        &[
+            "git-diff",
+            "left.json",
+            "left.json",
+            "0000000",
+            "100644",
+            "right.json",
+            "1111111",
+            "100644",
+            "renamed.json",
+            "similarity index 80%\nrename from left.json\nrename to renamed.json\n",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let out = stdout(&output);
+    let header: Vec<&str> = out.lines().take(6).collect();
+    assert_eq!(
+        header,
+        [
+            "diff --git a/left.json b/renamed.json",
+            "similarity index 80%",
+            "rename from left.json",
+            "rename to renamed.json",
+            "--- a/left.json",
+            "+++ b/renamed.json"
+        ]
+    );
+    assert!(out.contains("gadget"));
+}
+
+/// Two directories in a temporary directory, `left` and `right`, with the files of `files`
+/// as `(relative path, left content, right content)`, a `None` content leaves the file out.
+fn dirs(files: &[(&str, Option<&str>, Option<&str>)]) -> (TempDir, String, String) {
+    let tmp = TempDir::new().unwrap();
+    for (rel_path, left, right) in files {
+        for (side, content) in [("left", left), ("right", right)] {
+            if let Some(content) = content {
+                let path = tmp.path().join(side).join(rel_path);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, content).unwrap();
+            }
+        }
+    }
+    for side in ["left", "right"] {
+        std::fs::create_dir_all(tmp.path().join(side)).unwrap();
+    }
+    let left = tmp.path().join("left").display().to_string();
+    let right = tmp.path().join("right").display().to_string();
+    (tmp, left, right)
+}
+
+fn example_dirs() -> (TempDir, String, String) {
+    dirs(&[
+        ("a.json", Some(r#"{"x": 1}"#), Some(r#"{"x": 2}"#)),
+        ("same.json", Some(r#"{"k": 1}"#), Some(r#"{"k": 1}"#)),
+        ("old.json", Some("{}"), None),
+        ("new.json", None, Some("{}")),
+        (
+            "sub/b.json",
+            Some(r#"{"y": 1}"#),
+            Some(r#"{"y": 1, "z": 2}"#),
+        ),
+        ("tmp/c.json", Some("1"), Some("2")),
+        // not json, only `*.json` is compared by default
+        ("notes.txt", Some("a"), Some("b")),
+    ])
+}
+
+#[test]
+fn recursive_lists_the_files() {
+    let (_tmp, left, right) = example_dirs();
+    let output = run(&["diff", "--recursive", "--check", &left, &right], "");
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "~ a.json: 1 change\n\
+         + new.json: added\n\
+         - old.json: removed\n\
+         ~ sub/b.json: 1 change\n\
+         ~ tmp/c.json: 1 change\n\
+         3 changed, 1 added, 1 removed and 1 unchanged files\n"
+    );
+}
+
+#[test]
+fn recursive_prints_the_diff_of_changed_files() {
+    let (_tmp, left, right) = example_dirs();
+    let output = run(&["diff", "--recursive", &left, &right], "");
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let out = stdout(&output);
+    assert!(out.contains("3 changed, 1 added, 1 removed and 1 unchanged files"));
+    let sep = std::path::MAIN_SEPARATOR;
+    for file in ["a.json", &format!("sub{}b.json", sep)] {
+        assert!(
+            out.contains(&format!("--- {}{}{}", left, sep, file)),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains(&format!("+++ {}{}{}", right, sep, file)),
+            "{}",
+            out
+        );
+    }
+    assert!(
+        !out.contains(&format!("--- {}{}same.json", left, sep)),
+        "{}",
+        out
+    );
+    assert!(out.contains("+     z: 2"), "{}", out);
+}
+
+#[test]
+fn recursive_include_and_exclude() {
+    let (_tmp, left, right) = example_dirs();
+    let output = run(
+        &[
+            "diff",
+            "-r",
+            "--check",
+            "--exclude",
+            "tmp/**",
+            &left,
+            &right,
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    let out = stdout(&output);
+    assert!(!out.contains("tmp/c.json"), "{}", out);
+    assert!(out.ends_with("2 changed, 1 added, 1 removed and 1 unchanged files\n"));
+
+    let output = run(
+        &[
+            "diff",
+            "-r",
+            "--check",
+            "--include",
+            "sub/*.json",
+            &left,
+            &right,
+        ],
+        "",
+    );
+    assert_eq!(
+        stdout(&output),
+        "~ sub/b.json: 1 change\n1 changed, 0 added, 0 removed and 0 unchanged files\n"
+    );
+
+    // only the unchanged file is left
+    let output = run(&["diff", "-r", "--include", "same.json", &left, &right], "");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "0 changed, 0 added, 0 removed and 1 unchanged files\n"
+    );
+}
+
+#[test]
+fn recursive_exit_status() {
+    let (_tmp, left, right) = example_dirs();
+    let output = run(&["diff", "-r", "--quiet", &left, &left], "");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(stdout(&output).is_empty());
+    let output = run(&["diff", "-r", "--quiet", &left, &right], "");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).is_empty());
+    // a file only on one side is a difference
+    let output = run(
+        &[
+            "diff",
+            "-r",
+            "--quiet",
+            "--include",
+            "old.json",
+            &left,
+            &right,
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = run(&["diff", "-r", &left, "left.json"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("is not a directory"));
+}
+
+#[test]
+fn side_by_side() {
+    let output = run(
+        &[
+            "diff",
+            "--side-by-side",
+            "--width",
+            "41",
+            "left.json",
+            "right.json",
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    let out = stdout(&output);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 9, "{}", out);
+    assert!(lines[1].contains(" | "), "{}", out);
+    assert!(lines[6].contains(" >          \"c\""), "{}", out);
+    assert!(
+        lines.iter().all(|line| line.chars().count() <= 41),
+        "{}",
+        out
+    );
+}
+
+#[test]
+fn tree_options_need_the_tree_format() {
+    let cases: [&[&str]; 4] = [
+        &[
+            "diff",
+            "--side-by-side",
+            "--format",
+            "html",
+            "left.json",
+            "right.json",
+        ],
+        &[
+            "diff",
+            "--side-by-side",
+            "--format",
+            "json-patch",
+            "left.json",
+            "right.json",
+        ],
+        &[
+            "diff",
+            "--context",
+            "1",
+            "--format",
+            "dot",
+            "left.json",
+            "right.json",
+        ],
+        &[
+            "diff",
+            "--context",
+            "1",
+            "--format",
+            "locations",
+            "left.json",
+            "right.json",
+        ],
+    ];
+    for args in cases {
+        let output = run(args, "");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(stderr(&output).contains("only applies to"), "{:?}", args);
+    }
+    // the diff block of the markdown output shows the context
+    let args = [
+        "diff",
+        "--context",
+        "1",
+        "--format",
+        "markdown",
+        "--diff-block",
+        "left.json",
+        "right.json",
+    ];
+    assert_eq!(run(&args, "").status.code(), Some(1));
+}
+
+#[test]
+fn records_reject_the_comparison_options() {
+    let flags = [
+        ("--normalize=trim", "--normalize"),
+        ("--detect-renames", "--detect-renames"),
+        ("--null-as-missing", "--null-as-missing"),
+    ];
+    for format in ["table", "csv"] {
+        for (arg, flag) in flags {
+            let args = [
+                "diff",
+                "--format",
+                format,
+                "--record-key",
+                "/name",
+                arg,
+                "left.json",
+                "right.json",
+            ];
+            let output = run(&args, "");
+            assert_eq!(output.status.code(), Some(2), "{:?}", args);
+            assert!(
+                stderr(&output).contains(&format!("{} can not be used", flag)),
+                "{:?}",
+                args
+            );
+        }
+    }
+}
+
+#[test]
+fn similarity_prints_the_scores() {
+    let output = run(&["similarity", "left.json", "right.json"], "");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "0.7692\n");
+
+    // the most similar first
+    let output = run(&["similarity", "left.json", "right.json", "left.json"], "");
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "1.0000\tleft.json\n0.7692\tright.json\n");
+
+    let output = run(&["similarity", "left.json", "missing.json"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).is_empty());
+    assert!(stderr(&output).contains("missing.json"));
+}
+
+#[test]
+fn textconv_sorts_and_indents() {
+    let output = run(
+        &["textconv", "-"],
+        r#"{"b": [1, {"d": 1, "c": 2}], "a": null}"#,
+    );
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        r#"{
+  "a": null,
+  "b": [
+    1,
+    {
+      "c": 2,
+      "d": 1
+    }
+  ]
+}
+"#
+    );
+
+    let output = run(&["textconv", "invalid.json"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).is_empty());
+}